@@ -0,0 +1,114 @@
+use crate::layout::Layout;
+use crate::report::{self, Report, SCHEMA_VERSION};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Format of run directory names, e.g. "20261015T033200Z"
+pub const RUN_DIR_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Name of the symlink pointing at the most recent run
+pub const LATEST: &str = "latest";
+
+/// Create a fresh directory for a run started at `time`
+/// Runs within the same second get a numeric suffix
+fn create_run_dir(report_dir: &Path, time: DateTime<Utc>) -> Result<PathBuf> {
+    let name = time.format(RUN_DIR_FORMAT).to_string();
+
+    for attempt in 0.. {
+        let run_dir = match attempt {
+            0 => report_dir.join(&name),
+            n => report_dir.join(format!("{}-{}", name, n)),
+        };
+        match fs::create_dir(&run_dir) {
+            Ok(()) => return Ok(run_dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Could not create {:?}", run_dir));
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Point `latest` at the run directory, replacing any previous link
+fn update_latest(report_dir: &Path, run_dir: &Path) -> Result<()> {
+    let target = run_dir.file_name().context("Run directory has no name")?;
+    let latest = report_dir.join(LATEST);
+    let tmp = report_dir.join(format!(".{}.tmp", LATEST));
+    let _ = fs::remove_file(&tmp);
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, &tmp)
+        .with_context(|| format!("Could not create symlink {:?}", tmp))?;
+    // Without symlinks, record the name of the latest run instead
+    #[cfg(not(unix))]
+    fs::write(&tmp, target.to_string_lossy().as_bytes())
+        .with_context(|| format!("Could not write {:?}", tmp))?;
+
+    // Renaming over the old link keeps `latest` valid at all times
+    fs::rename(&tmp, &latest).with_context(|| format!("Could not update {:?}", latest))
+}
+
+/// Archive the full report (JSON and human text) in a timestamped run directory
+/// Returns the run directory
+pub fn write_report(report_dir: &Path, report: &Report, time: DateTime<Utc>) -> Result<PathBuf> {
+    fs::create_dir_all(report_dir)
+        .with_context(|| format!("Could not create report directory {:?}", report_dir))?;
+    let run_dir = create_run_dir(report_dir, time)?;
+
+    // Archives always use the current layout so they can be compared over time
+    let json = report::to_json(report, SCHEMA_VERSION)?;
+    fs::write(
+        run_dir.join("report.json"),
+        serde_json::to_string_pretty(&json)?,
+    )
+    .context("Could not write report.json")?;
+
+    let mut text = Vec::new();
+    report::render_human(
+        report,
+        &report::View::default(),
+        Layout::default(),
+        &mut text,
+    )?;
+    fs::write(run_dir.join("report.txt"), text).context("Could not write report.txt")?;
+
+    update_latest(report_dir, &run_dir)?;
+
+    Ok(run_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_write_report() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let report = Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            0,
+            Vec::new(),
+        );
+        let time = Utc.with_ymd_and_hms(2026, 10, 15, 3, 32, 0).unwrap();
+
+        let first = write_report(dir, &report, time).unwrap();
+        let second = write_report(dir, &report, time).unwrap();
+
+        assert_eq!(first, dir.join("20261015T033200Z"));
+        assert_eq!(second, dir.join("20261015T033200Z-1"));
+        assert!(first.join("report.json").exists());
+        assert!(first.join("report.txt").exists());
+
+        // latest follows the most recent run
+        let latest_json = fs::read_to_string(dir.join(LATEST).join("report.json")).unwrap();
+        let expected = fs::read_to_string(second.join("report.json")).unwrap();
+        assert_eq!(latest_json, expected);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::trace;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Set by `--timeout`, in seconds; 0 means no limit
+static TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+
+/// Set once Ctrl+C is pressed
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// How often a running child process is checked for timeout and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a network call may go without receiving anything before it fails
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A stage cut short by Ctrl+C or `--timeout`, as opposed to one that failed by itself
+#[derive(Debug)]
+pub enum Cancelled {
+    Interrupted { stage: String },
+    TimedOut { stage: String, seconds: u64 },
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cancelled::Interrupted { stage } => write!(f, "Interrupted during {}", stage),
+            Cancelled::TimedOut { stage, seconds } => {
+                write!(f, "{} timed out after {}s (see --timeout)", stage, seconds)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether an error, or any error it wraps, is a cancellation
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Limit every following cargo invocation and network call to `seconds`
+pub fn set_timeout(seconds: Option<u64>) {
+    TIMEOUT_SECS.store(seconds.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn timeout() -> Option<Duration> {
+    match TIMEOUT_SECS.load(Ordering::Relaxed) {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    // A second Ctrl+C does not wait for the clean shutdown
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// Turns Ctrl+C into a cancellation while alive; dropping it restores the default action
+pub struct Handler(());
+
+/// Turn Ctrl+C into a cancellation: running cargo processes and network calls are stopped
+/// and the current stage fails, so guards and cleanup still run instead of the process dying
+/// mid-way
+///
+/// Only for commands that poll `check` (directly or through `output` and `fetch`); anywhere
+/// else Ctrl+C would be ignored.
+pub fn install_handler() -> Handler {
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as *const () as libc::sighandler_t,
+        );
+    }
+    Handler(())
+}
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+}
+
+/// Fail if Ctrl+C was pressed, naming the stage that was cut short
+pub fn check(stage: &str) -> Result<()> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        return Err(Cancelled::Interrupted {
+            stage: stage.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Run a command to completion and capture its output, like `Command::output`, but stop it
+/// when `--timeout` runs out or Ctrl+C is pressed
+///
+/// `stage` names the step in errors, e.g. "cargo tree".
+pub fn output(command: &mut Command, stage: &str) -> Result<Output> {
+    let line = format!("{:?}", command);
+    trace::span(stage, &[("command", &line)], || {
+        output_within(command, stage, timeout())
+    })
+}
+
+fn output_within(command: &mut Command, stage: &str, timeout: Option<Duration>) -> Result<Output> {
+    check(stage)?;
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}. Is {} installed?", stage, program))?;
+    // Drained while waiting, so a chatty child cannot block on a full pipe
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .with_context(|| format!("Failed to wait for {}", stage))?
+        {
+            break status;
+        }
+        if let Err(interrupted) = check(stage) {
+            stop(&mut child);
+            return Err(interrupted);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            stop(&mut child);
+            return Err(Cancelled::TimedOut {
+                stage: stage.to_string(),
+                seconds: timeout.unwrap_or_default().as_secs(),
+            }
+            .into());
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    // Ctrl+C reaches the whole process group, so the child has most likely died of it too
+    check(stage)?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// HTTP agent for network calls, limited by `--timeout`
+fn agent() -> ureq::Agent {
+    let mut builder = ureq::AgentBuilder::new().timeout_read(READ_TIMEOUT);
+    if let Some(timeout) = timeout() {
+        builder = builder.timeout(timeout);
+    }
+    builder.build()
+}
+
+/// Run a network call with an agent limited by `--timeout`, failing as soon as Ctrl+C is
+/// pressed rather than when the call returns
+///
+/// The call runs on its own thread; once cancelled it is left to end on its read timeout.
+pub fn fetch<T: Send + 'static>(
+    stage: &str,
+    call: impl FnOnce(ureq::Agent) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    trace::span(stage, &[], || {
+        check(stage)?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(call(agent()));
+        });
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(result) => return result,
+                Err(RecvTimeoutError::Timeout) => check(stage)?,
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("{} failed", stage),
+            }
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_within() {
+        let mut echo = Command::new("sh");
+        echo.args(["-c", "echo out; echo err >&2"]);
+        let output = output_within(&mut echo, "sh", None).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        let started = Instant::now();
+        let error = output_within(
+            Command::new("sleep").arg("30"),
+            "sleep",
+            Some(Duration::from_secs(1)),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "sleep timed out after 1s (see --timeout)"
+        );
+        assert!(is_cancelled(&error.context("Could not sleep")));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let error =
+            output_within(&mut Command::new("/nonexistent/tool"), "tool", None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to run tool. Is /nonexistent/tool installed?"
+        );
+        assert!(!is_cancelled(&error));
+    }
+
+    #[test]
+    fn test_fetch() {
+        assert_eq!(fetch("lookup", |_| Ok(42)).unwrap(), 42);
+        let error = fetch::<()>("lookup", |_| anyhow::bail!("offline")).unwrap_err();
+        assert_eq!(error.to_string(), "offline");
+        let error = fetch::<()>("lookup", |_| panic!("broken")).unwrap_err();
+        assert_eq!(error.to_string(), "lookup failed");
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--offline`: cargo must not touch the network
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Make every following cargo invocation run with `net.offline=true`
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Whether a variable from the user's environment may be passed on to cargo
+///
+/// Only the target dir, build and terminal settings are dropped, since they change where
+/// cargo writes and what its output looks like. Registry, source replacement and network
+/// settings (`CARGO_REGISTRIES_*`, `CARGO_SOURCE_*`, `CARGO_NET_*`, ...) are kept: offline
+/// and mirror setups cannot resolve without them.
+fn keep_env(name: &str) -> bool {
+    name != "CARGO_TARGET_DIR"
+        && !name.starts_with("CARGO_BUILD_")
+        && !name.starts_with("CARGO_TERM_")
+}
+
+/// Throwaway target directory, so cargo never writes into the project's `target/`
+pub fn target_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("registry_checker_target_{}", std::process::id()))
+}
+
+/// Build a cargo invocation for a subcommand with colors forced off
+/// Colors are disabled regardless of the user's environment or cargo config,
+/// otherwise escape codes end up in the output we parse
+///
+/// Cargo runs in a scrubbed environment (see `keep_env`) with its own target directory.
+pub fn command(subcommand: &str) -> Command {
+    let mut command = Command::new("cargo");
+    for (name, _) in std::env::vars_os() {
+        if let Some(name) = name.to_str()
+            && !keep_env(name)
+        {
+            command.env_remove(name);
+        }
+    }
+    command
+        .env("CARGO_TERM_COLOR", "never")
+        .env("CARGO_TARGET_DIR", target_dir())
+        .env_remove("CLICOLOR_FORCE")
+        .arg(subcommand)
+        .arg("--color")
+        .arg("never");
+    if OFFLINE.load(Ordering::Relaxed) {
+        command.arg("--config").arg("net.offline=true");
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_env() {
+        assert!(keep_env("PATH"));
+        assert!(keep_env("CARGO_HOME"));
+        assert!(keep_env("CARGO_HTTP_PROXY"));
+        assert!(keep_env("CARGO_REGISTRIES_CRATES_IO_PROTOCOL"));
+        assert!(keep_env("CARGO_SOURCE_CRATES_IO_REPLACE_WITH"));
+        assert!(keep_env("CARGO_NET_OFFLINE"));
+        assert!(!keep_env("CARGO_TARGET_DIR"));
+        assert!(!keep_env("CARGO_BUILD_TARGET"));
+        assert!(!keep_env("CARGO_TERM_COLOR"));
+    }
+}
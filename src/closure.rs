@@ -0,0 +1,417 @@
+use crate::features;
+use crate::index::{Index, IndexVersion};
+use crate::registry::Registry;
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A crate the closure needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Needed {
+    pub name: String,
+    pub version: Version,
+    /// "name version" of the first crate found to need it; `None` for the root
+    pub required_by: Option<String>,
+    pub in_registry: bool,
+}
+
+/// A dependency the index snapshot has no matching version for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Unresolved {
+    pub name: String,
+    pub req: String,
+    pub required_by: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Closure {
+    pub crates: Vec<Needed>,
+    pub unresolved: Vec<Unresolved>,
+}
+
+type Key = (String, Version);
+
+/// Walks the index from the root crate, unifying features per crate version like cargo does
+struct Resolver<'a> {
+    index: &'a Index,
+    /// Versions already in the registry, preferred over newer index versions
+    registry: &'a HashMap<String, Vec<Version>>,
+    versions: HashMap<String, Vec<IndexVersion>>,
+    features: BTreeMap<Key, BTreeSet<String>>,
+    required_by: HashMap<Key, String>,
+    unresolved: BTreeSet<Unresolved>,
+}
+
+impl Resolver<'_> {
+    fn versions(&mut self, name: &str) -> Result<&[IndexVersion]> {
+        if !self.versions.contains_key(name) {
+            let versions = self.index.versions(name)?;
+            self.versions.insert(name.to_string(), versions);
+        }
+        Ok(&self.versions[name])
+    }
+
+    fn published(&mut self, key: &Key) -> Result<IndexVersion> {
+        self.versions(&key.0)?
+            .iter()
+            .find(|v| v.vers == key.1)
+            .cloned()
+            .with_context(|| format!("{} {} is not in the index snapshot", key.0, key.1))
+    }
+
+    /// Highest registry version matching the requirement, like cargo resolving against the
+    /// offline registry; otherwise the newest unyanked index version
+    fn select(&mut self, name: &str, req: &VersionReq) -> Result<Option<Version>> {
+        let registry = self.registry;
+        let versions = self.versions(name)?;
+        // Registry versions missing from the snapshot cannot be walked any further
+        let in_registry = registry
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|v| req.matches(v) && versions.iter().any(|i| i.vers == **v))
+            .max();
+        if let Some(version) = in_registry {
+            return Ok(Some(version.clone()));
+        }
+        Ok(versions
+            .iter()
+            .filter(|v| !v.yanked && req.matches(&v.vers))
+            .map(|v| v.vers.clone())
+            .max())
+    }
+
+    /// Enable features on a crate version; true when that enabled anything new
+    fn enable(&mut self, key: &Key, features: impl IntoIterator<Item = String>) -> bool {
+        let enabled = self.features.entry(key.clone()).or_default();
+        let before = enabled.len();
+        enabled.extend(features);
+        enabled.len() > before
+    }
+
+    /// Resolve the dependencies of one crate version under its enabled features,
+    /// returning the versions whose feature set grew
+    fn visit(&mut self, key: &Key) -> Result<Vec<Key>> {
+        let published = self.published(key)?;
+
+        // Expand the enabled features into optional dependencies and features on dependencies
+        let expansion = features::expand(
+            |feature| published.feature(feature),
+            |name| published.deps.iter().any(|d| d.optional && d.name == name),
+            self.features[key].iter().cloned(),
+        );
+        let (enabled_deps, dep_features) = (expansion.deps, expansion.dep_features);
+
+        let parent = format!("{} {}", key.0, key.1);
+        let mut grown = Vec::new();
+        for dep in &published.deps {
+            if dep.is_dev() || (dep.optional && !enabled_deps.contains(&dep.name)) {
+                continue;
+            }
+            let unresolved = || Unresolved {
+                name: dep.crate_name().to_string(),
+                req: dep.req.clone(),
+                required_by: parent.clone(),
+            };
+            let Ok(req) = VersionReq::parse(&dep.req) else {
+                self.unresolved.insert(unresolved());
+                continue;
+            };
+            let Some(version) = self.select(dep.crate_name(), &req)? else {
+                self.unresolved.insert(unresolved());
+                continue;
+            };
+
+            let child = (dep.crate_name().to_string(), version);
+            let features = dep
+                .features
+                .iter()
+                .chain(dep_features.get(&dep.name).into_iter().flatten())
+                .cloned()
+                .chain(dep.default_features.then(|| "default".to_string()));
+            let new = !self.features.contains_key(&child);
+            if self.enable(&child, features) || new {
+                self.required_by
+                    .entry(child.clone())
+                    .or_insert_with(|| parent.clone());
+                grown.push(child);
+            }
+        }
+        Ok(grown)
+    }
+}
+
+/// Every crate version building `name` `version` needs, according to the index snapshot
+///
+/// Dependencies resolve to registry versions where one matches. Dev-dependencies are left
+/// out; platform-specific dependencies are kept for every platform.
+pub fn resolve(
+    index: &Index,
+    registry: &HashMap<String, Vec<Version>>,
+    name: &str,
+    version: &Version,
+    features: &[String],
+    default_features: bool,
+) -> Result<Closure> {
+    let mut resolver = Resolver {
+        index,
+        registry,
+        versions: HashMap::new(),
+        features: BTreeMap::new(),
+        required_by: HashMap::new(),
+        unresolved: BTreeSet::new(),
+    };
+    let root = (name.to_string(), version.clone());
+    resolver.published(&root)?;
+    resolver.enable(
+        &root,
+        features
+            .iter()
+            .cloned()
+            .chain(default_features.then(|| "default".to_string())),
+    );
+
+    let mut pending = vec![root.clone()];
+    while let Some(key) = pending.pop() {
+        pending.extend(resolver.visit(&key)?);
+    }
+
+    let crates = resolver
+        .features
+        .keys()
+        .map(|key| Needed {
+            name: key.0.clone(),
+            version: key.1.clone(),
+            required_by: resolver.required_by.get(key).cloned(),
+            in_registry: false,
+        })
+        .collect();
+    Ok(Closure {
+        crates,
+        unresolved: resolver.unresolved.into_iter().collect(),
+    })
+}
+
+fn render(root: &str, closure: &Closure, out: &mut impl Write) -> io::Result<()> {
+    let missing: Vec<&Needed> = closure.crates.iter().filter(|c| !c.in_registry).collect();
+    writeln!(
+        out,
+        "Closure of {}: {} crate(s), {} missing from the registry",
+        root,
+        closure.crates.len(),
+        missing.len()
+    )?;
+
+    if !missing.is_empty() {
+        writeln!(out, "\nMissing from the registry:")?;
+        for needed in &missing {
+            match &needed.required_by {
+                Some(parent) => writeln!(
+                    out,
+                    "  {} {} (required by {})",
+                    needed.name, needed.version, parent
+                )?,
+                None => writeln!(out, "  {} {}", needed.name, needed.version)?,
+            }
+        }
+    }
+
+    if !closure.unresolved.is_empty() {
+        writeln!(out, "\nNot resolvable from the index snapshot:")?;
+        for dep in &closure.unresolved {
+            writeln!(
+                out,
+                "  {} {} (required by {})",
+                dep.name, dep.req, dep.required_by
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the closure of a crate version and fail unless the registry holds all of it
+pub fn print_closure(
+    registry_file: &Path,
+    index_dir: &Path,
+    name: &str,
+    version: &Version,
+    features: &[String],
+    default_features: bool,
+) -> Result<()> {
+    let registry = Registry::load(registry_file)?;
+    let index = Index::open(index_dir)?;
+    let mut closure = resolve(
+        &index,
+        &crate::registry_versions(&registry.entries),
+        name,
+        version,
+        features,
+        default_features,
+    )?;
+    for needed in &mut closure.crates {
+        needed.in_registry = registry
+            .entries
+            .contains(&format!("{}-{}.crate", needed.name, needed.version));
+    }
+
+    render(
+        &format!("{} {}", name, version),
+        &closure,
+        &mut io::stdout().lock(),
+    )?;
+
+    let missing = closure.crates.iter().filter(|c| !c.in_registry).count();
+    if missing > 0 || !closure.unresolved.is_empty() {
+        anyhow::bail!(
+            "{} crate(s) missing from the registry, {} dependency(ies) not resolvable",
+            missing,
+            closure.unresolved.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::crate_path;
+    use std::fs;
+
+    #[test]
+    fn test_resolve() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let write = |name: &str, lines: &[&str]| {
+            let path = dir.join(crate_path(name).unwrap());
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, lines.join("\n")).unwrap();
+        };
+        // One index line, wrapped here for readability
+        let tokio_line = r#"{"name":"tokio","vers":"1.40.0","deps":[
+            {"name":"mio","req":"^1","optional":true},
+            {"name":"bytes","req":"^1","optional":true},
+            {"name":"pin-project-lite","req":"^0.2"},
+            {"name":"tokio-test","req":"^0.4","kind":"dev"},
+            {"name":"missing","req":"^9","optional":true}],
+          "features":{"default":[],"net":["mio/net"],"io-util":["bytes"],"full":["net","io-util"]},
+          "features2":{"broken":["dep:missing"]}}"#
+            .replace('\n', "");
+        write("tokio", &[tokio_line.as_str()]);
+        write(
+            "mio",
+            &[
+                r#"{"name":"mio","vers":"1.0.2","deps":[{"name":"libc","req":"^0.2","optional":true}],"features":{"net":["dep:libc"]}}"#,
+                r#"{"name":"mio","vers":"1.0.3","yanked":true}"#,
+            ],
+        );
+        write(
+            "pin-project-lite",
+            &[
+                r#"{"name":"pin-project-lite","vers":"0.2.13"}"#,
+                r#"{"name":"pin-project-lite","vers":"0.2.14"}"#,
+            ],
+        );
+        write("libc", &[r#"{"name":"libc","vers":"0.2.158"}"#]);
+        write("bytes", &[r#"{"name":"bytes","vers":"1.7.1"}"#]);
+
+        let index = Index::open(dir).unwrap();
+        let empty = HashMap::new();
+        let tokio = Version::parse("1.40.0").unwrap();
+        let names = |closure: &Closure| -> Vec<String> {
+            closure
+                .crates
+                .iter()
+                .map(|c| format!("{} {}", c.name, c.version))
+                .collect()
+        };
+
+        let defaults = resolve(&index, &empty, "tokio", &tokio, &[], true).unwrap();
+        assert_eq!(
+            names(&defaults),
+            vec!["pin-project-lite 0.2.14", "tokio 1.40.0"]
+        );
+
+        // "net" enables optional mio with its own "net" feature, which pulls in libc
+        let net = resolve(&index, &empty, "tokio", &tokio, &["full".to_string()], true).unwrap();
+        assert_eq!(
+            names(&net),
+            vec![
+                "bytes 1.7.1",
+                "libc 0.2.158",
+                "mio 1.0.2",
+                "pin-project-lite 0.2.14",
+                "tokio 1.40.0"
+            ]
+        );
+        assert_eq!(net.crates[1].required_by.as_deref(), Some("mio 1.0.2"));
+        assert!(net.unresolved.is_empty());
+
+        // A compatible registry version wins over the newest one; others are ignored
+        let registry = HashMap::from([
+            (
+                "pin-project-lite".to_string(),
+                vec![Version::parse("0.2.13").unwrap()],
+            ),
+            ("bytes".to_string(), vec![Version::parse("0.9.0").unwrap()]),
+        ]);
+        let pinned = resolve(
+            &index,
+            &registry,
+            "tokio",
+            &tokio,
+            &["io-util".to_string()],
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            names(&pinned),
+            vec!["bytes 1.7.1", "pin-project-lite 0.2.13", "tokio 1.40.0"]
+        );
+
+        let broken = resolve(
+            &index,
+            &empty,
+            "tokio",
+            &tokio,
+            &["broken".to_string()],
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            broken.unresolved,
+            vec![Unresolved {
+                name: "missing".to_string(),
+                req: "^9".to_string(),
+                required_by: "tokio 1.40.0".to_string()
+            }]
+        );
+
+        assert!(
+            resolve(
+                &index,
+                &empty,
+                "tokio",
+                &Version::parse("9.0.0").unwrap(),
+                &[],
+                true
+            )
+            .is_err()
+        );
+
+        let mut out = Vec::new();
+        let mut closure = net;
+        closure.crates[3].in_registry = true;
+        closure.crates[4].in_registry = true;
+        render("tokio 1.40.0", &closure, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Closure of tokio 1.40.0: 5 crate(s), 3 missing from the registry\n\
+             \nMissing from the registry:\n\
+             \x20 bytes 1.7.1 (required by tokio 1.40.0)\n\
+             \x20 libc 0.2.158 (required by mio 1.0.2)\n\
+             \x20 mio 1.0.2 (required by tokio 1.40.0)\n"
+        );
+    }
+}
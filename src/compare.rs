@@ -0,0 +1,263 @@
+use crate::lockfile;
+use crate::registry::Registry;
+use crate::resolve_dependencies;
+use crate::workspace::{Selection, Workspace};
+use anyhow::Result;
+use semver::Version;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// One side of a comparison: the targets and features cargo resolves for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Configuration {
+    pub targets: Vec<String>,
+    /// Features to enable, e.g. "tls,json"; empty for the default features
+    pub features: String,
+}
+
+impl Configuration {
+    /// Build the two sides from `--target` / `--features` given twice
+    pub fn pair(targets: &[String], features: &[String]) -> Result<[Configuration; 2]> {
+        let side = |target: Option<&String>, features: Option<&String>| Configuration {
+            targets: target.cloned().into_iter().collect(),
+            features: features.cloned().unwrap_or_default(),
+        };
+        match (targets, features) {
+            ([a, b], [] | [_]) => Ok([
+                side(Some(a), features.first()),
+                side(Some(b), features.first()),
+            ]),
+            ([] | [_], [a, b]) => Ok([
+                side(targets.first(), Some(a)),
+                side(targets.first(), Some(b)),
+            ]),
+            _ => anyhow::bail!("Give either two --target or two --features values to compare"),
+        }
+    }
+
+    fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.targets.is_empty() {
+            parts.push(format!("target {}", self.targets.join(", ")));
+        }
+        if !self.features.is_empty() {
+            parts.push(format!("features {}", self.features));
+        } else if parts.is_empty() {
+            parts.push("default features".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// A crate resolved differently by the two configurations
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub name: String,
+    pub a: Option<Version>,
+    pub b: Option<Version>,
+}
+
+/// Crates only one side resolves, or resolves to another version, sorted by name
+fn diff(a: &HashMap<String, Version>, b: &HashMap<String, Version>) -> Vec<Difference> {
+    let names: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    names
+        .into_iter()
+        .filter(|name| a.get(*name) != b.get(*name))
+        .map(|name| Difference {
+            name: name.clone(),
+            a: a.get(name).cloned(),
+            b: b.get(name).cloned(),
+        })
+        .collect()
+}
+
+fn render(
+    labels: [&str; 2],
+    differences: &[Difference],
+    shared: usize,
+    registry: Option<&Registry>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let tag = |name: &str, version: &Version| match registry {
+        Some(registry)
+            if !registry
+                .entries
+                .contains(&format!("{}-{}.crate", name, version)) =>
+        {
+            " [missing from registry]"
+        }
+        _ => "",
+    };
+
+    writeln!(out, "Comparing {} with {}:", labels[0], labels[1])?;
+    for (index, label) in labels.iter().enumerate() {
+        let only: Vec<(&str, &Version)> = differences
+            .iter()
+            .filter_map(|d| match (index, &d.a, &d.b) {
+                (0, Some(a), None) => Some((d.name.as_str(), a)),
+                (1, None, Some(b)) => Some((d.name.as_str(), b)),
+                _ => None,
+            })
+            .collect();
+        if !only.is_empty() {
+            writeln!(out, "\nOnly with {} ({}):", label, only.len())?;
+            for (name, version) in only {
+                writeln!(out, "  {} v{}{}", name, version, tag(name, version))?;
+            }
+        }
+    }
+
+    let changed: Vec<&Difference> = differences
+        .iter()
+        .filter(|d| d.a.is_some() && d.b.is_some())
+        .collect();
+    if !changed.is_empty() {
+        writeln!(out, "\nDifferent versions ({}):", changed.len())?;
+        for d in changed {
+            let (Some(a), Some(b)) = (&d.a, &d.b) else {
+                continue;
+            };
+            writeln!(
+                out,
+                "  {} v{}{} / v{}{}",
+                d.name,
+                a,
+                tag(&d.name, a),
+                b,
+                tag(&d.name, b)
+            )?;
+        }
+    }
+
+    writeln!(
+        out,
+        "\n{} crate(s) differ, {} resolved identically by both",
+        differences.len(),
+        shared
+    )?;
+    Ok(())
+}
+
+/// Resolve the project for both configurations and print how the required crates differ
+pub fn compare(
+    manifest_path: &PathBuf,
+    selection: &Selection,
+    configurations: &[Configuration; 2],
+    registry_file: Option<&Path>,
+) -> Result<()> {
+    let workspace = Workspace::load(manifest_path)?;
+    let lockfile = lockfile::lockfile_path(&workspace.root_manifest);
+    lockfile::check_drift(manifest_path, &lockfile)?;
+    let _generated = lockfile::GeneratedGuard::new(&lockfile);
+
+    let mut resolved = Vec::new();
+    for configuration in configurations {
+        eprintln!("Resolving dependencies for {}...", configuration.label());
+        let mut deps = resolve_dependencies(
+            manifest_path,
+            selection,
+            &configuration.targets,
+            &configuration.features,
+            lockfile.exists(),
+        )?;
+        for member in &workspace.members {
+            deps.remove(&member.name);
+        }
+        resolved.push(deps);
+    }
+
+    let differences = diff(&resolved[0], &resolved[1]);
+    let shared = resolved[0]
+        .iter()
+        .filter(|(name, version)| resolved[1].get(*name) == Some(version))
+        .count();
+    let registry = registry_file.map(Registry::load).transpose()?;
+    let labels = [configurations[0].label(), configurations[1].label()];
+
+    render(
+        [&labels[0], &labels[1]],
+        &differences,
+        shared,
+        registry.as_ref(),
+        &mut io::stdout().lock(),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::RegistryFormat;
+
+    fn deps(list: &[(&str, &str)]) -> HashMap<String, Version> {
+        list.iter()
+            .map(|(name, version)| (name.to_string(), Version::parse(version).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_configuration_pair() {
+        let linux = "x86_64-unknown-linux-gnu".to_string();
+        let windows = "x86_64-pc-windows-msvc".to_string();
+
+        let [a, b] = Configuration::pair(&[linux.clone(), windows.clone()], &[]).unwrap();
+        assert_eq!(a.label(), "target x86_64-unknown-linux-gnu");
+        assert_eq!(b.targets, vec![windows]);
+
+        let [a, b] = Configuration::pair(&[linux], &[String::new(), "tls".to_string()]).unwrap();
+        assert_eq!(a.targets, b.targets);
+        assert_eq!(a.label(), "target x86_64-unknown-linux-gnu");
+        assert_eq!(b.label(), "target x86_64-unknown-linux-gnu, features tls");
+
+        let [a, _] = Configuration::pair(&[], &[String::new(), "tls".to_string()]).unwrap();
+        assert_eq!(a.label(), "default features");
+
+        assert!(Configuration::pair(&[], &[]).is_err());
+        assert!(
+            Configuration::pair(
+                &["a".to_string(), "b".to_string()],
+                &["x".to_string(), "y".to_string()]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_diff_and_render() {
+        let a = deps(&[("serde", "1.0.228"), ("libc", "0.2.170"), ("mio", "1.0.0")]);
+        let b = deps(&[
+            ("serde", "1.0.228"),
+            ("windows-sys", "0.59.0"),
+            ("mio", "1.0.1"),
+        ]);
+
+        let differences = diff(&a, &b);
+        let names: Vec<&str> = differences.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["libc", "mio", "windows-sys"]);
+
+        let mut registry = Registry::new(RegistryFormat::Text);
+        registry.entries.extend([
+            "libc-0.2.170.crate".to_string(),
+            "mio-1.0.0.crate".to_string(),
+        ]);
+
+        let mut out = Vec::new();
+        render(
+            ["linux", "windows"],
+            &differences,
+            1,
+            Some(&registry),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Comparing linux with windows:\n\
+             \nOnly with linux (1):\n  libc v0.2.170\n\
+             \nOnly with windows (1):\n  windows-sys v0.59.0 [missing from registry]\n\
+             \nDifferent versions (1):\n  mio v1.0.0 / v1.0.1 [missing from registry]\n\
+             \n3 crate(s) differ, 1 resolved identically by both\n"
+        );
+    }
+}
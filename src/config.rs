@@ -0,0 +1,274 @@
+use crate::owners::glob_match;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Settings loaded from the `--config` TOML file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// REST template used by `--create-ticket`
+    pub ticket: Option<TicketConfig>,
+    /// Where fleet jobs get the tool from, used by `self check-version`
+    pub distribution: Option<DistributionConfig>,
+    /// Extra crates provided by the toolchain or patched internally (wildcards allowed)
+    #[serde(default)]
+    pub provided: Vec<String>,
+    /// Registry listing per environment (dev, staging, production, ...), selected with `--env`
+    #[serde(default)]
+    pub environments: BTreeMap<String, EnvironmentConfig>,
+    /// How entries move between environments with `promote`
+    #[serde(default)]
+    pub promotion: PromotionConfig,
+    /// Missing crates not to report, each with a reason and optionally an expiry date
+    #[serde(default)]
+    pub ignore: Vec<IgnoreEntry>,
+    /// Download URL template of the internal crate mirror, shown by `--show-urls`;
+    /// `{name}` and `{version}` are substituted
+    pub mirror_url: Option<String>,
+}
+
+/// A temporary exception for a missing crate
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreEntry {
+    /// Crate name pattern (`*` and `?` wildcards allowed)
+    #[serde(rename = "crate")]
+    pub name: String,
+    /// Only ignore versions matching this requirement
+    pub version: Option<VersionReq>,
+    /// Last day the entry applies; afterwards the crate is reported again
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub until: Option<NaiveDate>,
+    /// Why the crate is ignored, e.g. a ticket number
+    pub reason: String,
+}
+
+impl IgnoreEntry {
+    pub fn matches(&self, name: &str, version: &Version) -> bool {
+        glob_match(&self.name, name) && self.version.as_ref().is_none_or(|req| req.matches(version))
+    }
+
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.until.is_some_and(|until| until < today)
+    }
+}
+
+/// A `YYYY-MM-DD` date, quoted or as a TOML local date
+fn deserialize_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error> {
+    let date = match toml::Value::deserialize(deserializer)? {
+        toml::Value::String(date) => date,
+        toml::Value::Datetime(date) => date.to_string(),
+        other => {
+            return Err(serde::de::Error::custom(format!(
+                "expected a YYYY-MM-DD date, found {}",
+                other
+            )));
+        }
+    };
+    NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|err| serde::de::Error::custom(format!("invalid date {:?}: {}", date, err)))
+}
+
+/// One environment's offline registry
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentConfig {
+    pub registry_file: PathBuf,
+    /// Environment `promote` copies entries to by default
+    pub promote_to: Option<String>,
+    /// Checks entries must pass to be promoted into this environment
+    #[serde(default)]
+    pub gates: GateConfig,
+}
+
+/// Promotion gates; unset gates are not checked
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateConfig {
+    /// Checkout of the RustSec advisory database; affected versions are refused
+    pub advisory_db: Option<PathBuf>,
+    /// SPDX license identifiers allowed (from the `license` of YAML registry entries)
+    pub allowed_licenses: Option<Vec<String>>,
+    /// Days an entry must have spent in the source environment, per the audit log
+    pub min_soak_days: Option<u32>,
+}
+
+/// Settings for `promote`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromotionConfig {
+    /// JSON-lines file every promoted entry is recorded in
+    pub audit_log: Option<PathBuf>,
+}
+
+/// Internal distribution of the tool itself
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DistributionConfig {
+    /// URL returning the latest distributed version, as plain text or JSON `{"version": ...}`
+    pub url: Option<String>,
+    /// Versions of the tool allowed to produce reports, e.g. ">=0.3, <0.4"
+    pub required_version: Option<VersionReq>,
+}
+
+/// REST request template for filing an approval ticket
+///
+/// `url`, `headers` and `body` may reference environment variables as `${VAR}`
+/// and report values as `{{placeholder}}` (see the ticket module)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TicketConfig {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+impl Config {
+    /// Load the config file, or the default (empty) config when no path is given
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not read config file {:?}", path))?;
+        toml::from_str(&content).with_context(|| format!("Could not parse config file {:?}", path))
+    }
+
+    /// Look up an environment by name
+    pub fn environment(&self, name: &str) -> Result<&EnvironmentConfig> {
+        self.environments.get(name).with_context(|| {
+            let known: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+            format!(
+                "Unknown environment {:?} (configured: {})",
+                name,
+                if known.is_empty() {
+                    "none, add [environments.<name>] to the config file".to_string()
+                } else {
+                    known.join(", ")
+                }
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [ticket]
+            url = "https://jira.example.com/rest/api/2/issue"
+            headers = { Authorization = "Bearer ${JIRA_TOKEN}" }
+            body = '{"fields": {"summary": "{{summary}}"}}'
+            "#,
+        )
+        .unwrap();
+
+        let ticket = config.ticket.unwrap();
+        assert_eq!(ticket.method, "POST");
+        assert_eq!(ticket.headers["Authorization"], "Bearer ${JIRA_TOKEN}");
+
+        let config: Config = toml::from_str(
+            r#"
+            provided = ["internal-*"]
+            mirror_url = "https://mirror.example.com/crates/{name}/{version}/download"
+
+            [distribution]
+            url = "https://tools.example.com/registry_checker/latest"
+            required_version = ">=0.2"
+            "#,
+        )
+        .unwrap();
+        let distribution = config.distribution.unwrap();
+        assert_eq!(distribution.required_version.unwrap().to_string(), ">=0.2");
+        assert_eq!(config.provided, vec!["internal-*"]);
+        assert_eq!(
+            config.mirror_url.as_deref(),
+            Some("https://mirror.example.com/crates/{name}/{version}/download")
+        );
+
+        let config: Config = toml::from_str(
+            r#"
+            [environments.dev]
+            registry_file = "registries/dev.txt"
+            promote_to = "staging"
+
+            [environments.staging]
+            registry_file = "registries/staging.yaml"
+
+            [environments.staging.gates]
+            allowed_licenses = ["MIT", "Apache-2.0"]
+            min_soak_days = 7
+
+            [promotion]
+            audit_log = "registries/audit.jsonl"
+            "#,
+        )
+        .unwrap();
+        let dev = config.environment("dev").unwrap();
+        assert_eq!(dev.registry_file, PathBuf::from("registries/dev.txt"));
+        assert_eq!(dev.promote_to.as_deref(), Some("staging"));
+        let staging = config.environment("staging").unwrap();
+        assert!(staging.promote_to.is_none());
+        assert_eq!(staging.gates.min_soak_days, Some(7));
+        assert!(staging.gates.advisory_db.is_none());
+        assert!(dev.gates.allowed_licenses.is_none());
+        assert!(config.environment("prod").is_err());
+        assert_eq!(
+            config.promotion.audit_log,
+            Some(PathBuf::from("registries/audit.jsonl"))
+        );
+
+        let config: Config = toml::from_str(
+            r#"
+            ignore = [
+                { crate = "foo", until = "2025-12-31", reason = "ticket-123" },
+                { crate = "windows-*", version = "<0.60", until = 2026-03-01, reason = "Windows support lands in Q1" },
+                { crate = "bar", reason = "vendored" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let day = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let v = |version: &str| Version::parse(version).unwrap();
+        let [foo, windows, bar] = &config.ignore[..] else {
+            panic!("expected three ignore entries");
+        };
+        assert_eq!(foo.until, Some(day("2025-12-31")));
+        assert!(!foo.is_expired(day("2025-12-31")));
+        assert!(foo.is_expired(day("2026-01-01")));
+        assert!(windows.matches("windows-sys", &v("0.59.0")));
+        assert!(!windows.matches("windows-sys", &v("0.60.0")));
+        assert_eq!(windows.until, Some(day("2026-03-01")));
+        assert!(!bar.is_expired(day("2100-01-01")));
+        assert!(toml::from_str::<Config>("ignore = [{ crate = \"foo\" }]").is_err());
+        assert!(
+            toml::from_str::<Config>(
+                "ignore = [{ crate = \"foo\", until = \"soon\", reason = \"x\" }]"
+            )
+            .is_err()
+        );
+
+        // Unknown keys are rejected so typos don't go unnoticed
+        assert!(toml::from_str::<Config>("[tickets]\nurl = \"x\"").is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use crate::fleet::ProjectErrors;
+use crate::lockfile;
+use crate::metadata::DependencyGraph;
+use crate::platform::Targets;
+use crate::registry::Registry;
+use crate::report::csv_field;
+use crate::seed;
+use crate::workspace::Workspace;
+use anyhow::Result;
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// What a set of enabled features turns on in a package
+#[derive(Debug, Default)]
+pub struct Expansion {
+    /// The features themselves and every feature they enable
+    pub features: BTreeSet<String>,
+    /// Optional dependencies enabled, by the name used in the feature table
+    pub deps: HashSet<String>,
+    /// Features enabled on dependencies ("dep/feature" and "dep?/feature")
+    pub dep_features: HashMap<String, Vec<String>>,
+}
+
+/// Expand features through a package's feature table, the way cargo does
+///
+/// `table` looks up what a feature enables, `optional` tells whether a name is an optional
+/// dependency (which is also an implicit feature of the same name).
+pub fn expand<'a>(
+    table: impl Fn(&str) -> Option<&'a [String]>,
+    optional: impl Fn(&str) -> bool,
+    start: impl IntoIterator<Item = String>,
+) -> Expansion {
+    let mut expansion = Expansion::default();
+    let mut pending: Vec<String> = start.into_iter().collect();
+    while let Some(feature) = pending.pop() {
+        if !expansion.features.insert(feature.clone()) {
+            continue;
+        }
+        match table(&feature) {
+            Some(items) => {
+                for item in items {
+                    if let Some(dep) = item.strip_prefix("dep:") {
+                        expansion.deps.insert(dep.to_string());
+                    } else if let Some((dep, dep_feature)) = item.split_once('/') {
+                        // "dep?/feature" only applies when something else enables dep
+                        let dep = match dep.strip_suffix('?') {
+                            Some(weak) => weak,
+                            None => {
+                                expansion.deps.insert(dep.to_string());
+                                dep
+                            }
+                        };
+                        expansion
+                            .dep_features
+                            .entry(dep.to_string())
+                            .or_default()
+                            .push(dep_feature.to_string());
+                    } else {
+                        pending.push(item.clone());
+                    }
+                }
+            }
+            None if optional(&feature) => {
+                expansion.deps.insert(feature);
+            }
+            None => {}
+        }
+    }
+    expansion
+}
+
+/// How the scanned projects use the features of one registry crate
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureUsage {
+    pub name: String,
+    pub version: Version,
+    /// Projects resolving this crate version
+    pub projects: usize,
+    /// Enabled features with the number of projects enabling each
+    pub enabled: BTreeMap<String, usize>,
+    /// Features of the crate (besides `default`) no project enables
+    pub unused: BTreeSet<String>,
+    /// Crates whose default features alone pull this one in, when that holds in every project
+    pub default_only_via: Option<BTreeSet<String>>,
+}
+
+/// Accumulates feature usage of registry crates across projects
+#[derive(Debug, Default)]
+struct Usage {
+    crates: BTreeMap<(String, Version), (FeatureUsage, BTreeSet<String>)>,
+}
+
+impl Usage {
+    fn add_project(&mut self, graph: &DependencyGraph, registry: &Registry) {
+        // Only what the project resolves on the host, not e.g. Windows-only crates
+        let members: Vec<&str> = graph
+            .packages()
+            .filter(|package| graph.is_member(&package.id))
+            .map(|package| package.name.as_str())
+            .collect();
+        for package in graph.reachable(&members) {
+            let crate_file = format!("{}-{}.crate", package.name, package.version);
+            if graph.is_member(&package.id) || !registry.entries.contains(&crate_file) {
+                continue;
+            }
+            let (usage, available) = self
+                .crates
+                .entry((package.name.clone(), package.version.clone()))
+                .or_insert_with(|| {
+                    let usage = FeatureUsage {
+                        name: package.name.clone(),
+                        version: package.version.clone(),
+                        projects: 0,
+                        enabled: BTreeMap::new(),
+                        unused: BTreeSet::new(),
+                        default_only_via: Some(BTreeSet::new()),
+                    };
+                    (usage, BTreeSet::new())
+                });
+
+            usage.projects += 1;
+            available.extend(package.features.keys().cloned());
+            for feature in graph.features(&package.id) {
+                *usage.enabled.entry(feature.clone()).or_default() += 1;
+            }
+            usage.default_only_via = match (
+                usage.default_only_via.take(),
+                graph.default_only_parents(&package.id),
+            ) {
+                (Some(mut via), Some(parents)) => {
+                    via.extend(parents);
+                    Some(via)
+                }
+                _ => None,
+            };
+        }
+    }
+
+    fn finish(self) -> Vec<FeatureUsage> {
+        self.crates
+            .into_values()
+            .map(|(mut usage, available)| {
+                usage.unused = available
+                    .into_iter()
+                    .filter(|feature| feature != "default" && !usage.enabled.contains_key(feature))
+                    .collect();
+                usage
+            })
+            .collect()
+    }
+}
+
+fn render_text(usage: &[FeatureUsage], out: &mut impl Write) -> io::Result<()> {
+    for crate_usage in usage {
+        writeln!(
+            out,
+            "{} {} (used by {} project(s))",
+            crate_usage.name, crate_usage.version, crate_usage.projects
+        )?;
+        let enabled: Vec<String> = crate_usage
+            .enabled
+            .iter()
+            .map(|(feature, count)| format!("{} ({})", feature, count))
+            .collect();
+        if !enabled.is_empty() {
+            writeln!(out, "  enabled: {}", enabled.join(", "))?;
+        }
+        if !crate_usage.unused.is_empty() {
+            let unused: Vec<&str> = crate_usage.unused.iter().map(String::as_str).collect();
+            writeln!(out, "  unused: {}", unused.join(", "))?;
+        }
+        if let Some(via) = &crate_usage.default_only_via {
+            let via: Vec<&str> = via.iter().map(String::as_str).collect();
+            writeln!(
+                out,
+                "  only pulled in by the default features of {}",
+                via.join(", ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn render_csv(usage: &[FeatureUsage], out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "crate,version,projects,enabled,unused,default_only_via"
+    )?;
+    for crate_usage in usage {
+        let enabled: Vec<String> = crate_usage
+            .enabled
+            .iter()
+            .map(|(feature, count)| format!("{}={}", feature, count))
+            .collect();
+        let unused: Vec<&str> = crate_usage.unused.iter().map(String::as_str).collect();
+        let via: Vec<&str> = crate_usage
+            .default_only_via
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            csv_field(&crate_usage.name),
+            crate_usage.version,
+            crate_usage.projects,
+            csv_field(&enabled.join(" ")),
+            csv_field(&unused.join(" ")),
+            csv_field(&via.join(" "))
+        )?;
+    }
+    Ok(())
+}
+
+/// Report which features the projects matching the manifest globs enable on registry crates
+///
+/// Projects that fail to resolve are skipped and listed at the end unless `fail_fast` is set.
+pub fn print_feature_usage(
+    registry_file: &Path,
+    patterns: &[String],
+    csv: bool,
+    fail_fast: bool,
+) -> Result<()> {
+    let registry = Registry::load(registry_file)?;
+    let targets = Targets::load(&[])?;
+    let mut errors = ProjectErrors::new(fail_fast);
+
+    let mut manifests = 0;
+    let mut roots = BTreeSet::new();
+    for pattern in patterns {
+        for manifest in seed::glob_files(pattern)? {
+            manifests += 1;
+            // Every member manifest of a workspace resolves the same graph
+            if let Some(workspace) = errors.check(&manifest, Workspace::load(&manifest))? {
+                roots.insert(workspace.root_manifest);
+            }
+        }
+    }
+    if manifests == 0 {
+        anyhow::bail!("No manifests match {}", patterns.join(", "));
+    }
+
+    let mut usage = Usage::default();
+    let mut scanned = 0;
+    for root in &roots {
+        eprintln!("Resolving {:?}...", root);
+        let lockfile = lockfile::lockfile_path(root);
+        let _generated = lockfile::GeneratedGuard::new(&lockfile);
+        if let Some(graph) = errors.check(
+            root,
+            DependencyGraph::load(root, &targets, lockfile.exists()),
+        )? {
+            usage.add_project(&graph, &registry);
+            scanned += 1;
+        }
+    }
+    let usage = usage.finish();
+    eprintln!(
+        "{} of {} registry entries are used by the {} scanned project(s)",
+        usage.len(),
+        registry.len(),
+        scanned
+    );
+
+    let mut out = io::stdout().lock();
+    if csv {
+        render_csv(&usage, &mut out)?;
+        // Keep the CSV parseable
+        errors.render(&mut io::stderr())?;
+    } else {
+        render_text(&usage, &mut out)?;
+        errors.render(&mut out)?;
+    }
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let table: BTreeMap<String, Vec<String>> = [
+            ("default", vec!["std", "derive"]),
+            ("std", vec!["alloc", "serde?/std"]),
+            ("alloc", vec![]),
+            ("derive", vec!["dep:serde_derive"]),
+            ("rc", vec!["serde/rc"]),
+        ]
+        .into_iter()
+        .map(|(name, items)| {
+            (
+                name.to_string(),
+                items.into_iter().map(str::to_string).collect(),
+            )
+        })
+        .collect();
+        let expanded = expand(
+            |feature| table.get(feature).map(Vec::as_slice),
+            |name| ["serde", "serde_derive", "log"].contains(&name),
+            ["default".to_string(), "log".to_string()],
+        );
+
+        assert_eq!(
+            expanded.features.into_iter().collect::<Vec<_>>(),
+            vec!["alloc", "default", "derive", "log", "std"]
+        );
+        let mut deps: Vec<String> = expanded.deps.into_iter().collect();
+        deps.sort();
+        // serde is only weakly referenced, log is an implicit feature
+        assert_eq!(deps, vec!["log", "serde_derive"]);
+        assert_eq!(expanded.dep_features["serde"], vec!["std"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let usage = vec![FeatureUsage {
+            name: "hyper".to_string(),
+            version: Version::parse("1.0.0").unwrap(),
+            projects: 3,
+            enabled: [("default".to_string(), 3), ("http1".to_string(), 2)]
+                .into_iter()
+                .collect(),
+            unused: ["unstable".to_string()].into_iter().collect(),
+            default_only_via: Some(["axum".to_string()].into_iter().collect()),
+        }];
+
+        let mut out = Vec::new();
+        render_text(&usage, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "hyper 1.0.0 (used by 3 project(s))\n\
+             \x20 enabled: default (3), http1 (2)\n\
+             \x20 unused: unstable\n\
+             \x20 only pulled in by the default features of axum\n"
+        );
+
+        let mut out = Vec::new();
+        render_csv(&usage, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().nth(1),
+            Some("hyper,1.0.0,3,default=3 http1=2,unstable,axum")
+        );
+    }
+}
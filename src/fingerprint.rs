@@ -0,0 +1,180 @@
+use crate::registry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Round constants of SHA-256
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 of the data as lowercase hex, the digest `sha256sum` prints
+pub fn sha256(data: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // Padding: a 1 bit, zeros up to 56 mod 64 bytes, then the length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(add);
+        }
+    }
+
+    state.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let data = fs::read(path).with_context(|| format!("Could not read {:?}", path))?;
+    Ok(sha256(&data))
+}
+
+/// The tool and inputs a report was produced from, so an archived report can be traced
+/// back to exactly what was checked
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Fingerprint {
+    /// Version of registry_checker that produced the report
+    pub tool_version: String,
+    /// SHA-256 of the registry listing
+    pub registry_sha256: String,
+    /// SHA-256 of the project's Cargo.lock; absent when the project has none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockfile_sha256: Option<String>,
+    /// Command-line arguments the tool was run with
+    pub arguments: Vec<String>,
+    /// When the check ran (RFC 3339, UTC)
+    pub generated_at: String,
+}
+
+impl Fingerprint {
+    /// Fingerprint the inputs of a check; `lockfile` is None when the project has none
+    pub fn new(
+        registry_file: &Path,
+        lockfile: Option<&Path>,
+        arguments: Vec<String>,
+        time: DateTime<Utc>,
+    ) -> Result<Fingerprint> {
+        Ok(Fingerprint {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            registry_sha256: sha256(registry::read_listing(registry_file)?.as_bytes()),
+            lockfile_sha256: lockfile.map(sha256_file).transpose()?,
+            arguments,
+            generated_at: time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        })
+    }
+
+    /// Header lines for text reports
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("Generated by registry_checker {}", self.tool_version),
+            format!("  at {}", self.generated_at),
+            format!("  arguments: {}", self.arguments.join(" ")),
+            format!("  registry sha256: {}", self.registry_sha256),
+            format!(
+                "  lockfile sha256: {}",
+                self.lockfile_sha256.as_deref().unwrap_or("none")
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks of padding
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            sha256(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("registry.txt");
+        fs::write(&path, "abc").unwrap();
+        let time = Utc.with_ymd_and_hms(2026, 10, 15, 8, 30, 0).unwrap();
+        let fingerprint = Fingerprint::new(
+            &path,
+            None,
+            vec!["-r".to_string(), "reg.txt".to_string()],
+            time,
+        )
+        .unwrap();
+        assert_eq!(
+            fingerprint.registry_sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(fingerprint.generated_at, "2026-10-15T08:30:00Z");
+        assert_eq!(fingerprint.lines()[2], "  arguments: -r reg.txt");
+        assert_eq!(fingerprint.lines()[4], "  lockfile sha256: none");
+    }
+}
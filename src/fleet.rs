@@ -0,0 +1,128 @@
+use crate::cancel;
+use anyhow::Result;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A project of a multi-project scan that could not be processed
+#[derive(Debug)]
+pub struct ProjectError {
+    pub path: PathBuf,
+    pub error: anyhow::Error,
+}
+
+/// Per-project failures of a scan over many projects
+///
+/// By default a broken project is recorded and skipped so the others are still scanned;
+/// with `--fail-fast` the first failure aborts the run. Ctrl+C and `--timeout` always do.
+#[derive(Debug)]
+pub struct ProjectErrors {
+    fail_fast: bool,
+    errors: Vec<ProjectError>,
+}
+
+impl ProjectErrors {
+    pub fn new(fail_fast: bool) -> ProjectErrors {
+        ProjectErrors {
+            fail_fast,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The value of a successful step, or None after recording the failure of `path`
+    ///
+    /// Returns the error itself when failing fast or when the scan was cancelled.
+    pub fn check<T>(&mut self, path: &Path, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.fail_fast || cancel::is_cancelled(&error) => Err(error),
+            Err(error) => {
+                let message = format!("{:#}", error);
+                eprintln!(
+                    "Skipping {:?}: {}",
+                    path,
+                    message.lines().next().unwrap_or_default()
+                );
+                self.errors.push(ProjectError {
+                    path: path.to_path_buf(),
+                    error,
+                });
+                Ok(None)
+            }
+        }
+    }
+
+    /// Errors section listing every skipped project; nothing when all succeeded
+    pub fn render(&self, out: &mut impl Write) -> io::Result<()> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "\nErrors ({} project(s) skipped):", self.errors.len())?;
+        for ProjectError { path, error } in &self.errors {
+            // cargo's stderr spans several lines
+            let message = format!("{:#}", error);
+            writeln!(
+                out,
+                "  {}: {}",
+                path.display(),
+                message.trim_end().replace('\n', "\n    ")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Fail the run if any project was skipped, once the results of the others are out
+    pub fn finish(self) -> Result<()> {
+        match self.errors.len() {
+            0 => Ok(()),
+            skipped => anyhow::bail!(
+                "{} project(s) could not be scanned (use --fail-fast to stop at the first one)",
+                skipped
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_project_errors() {
+        let mut errors = ProjectErrors::new(false);
+        assert_eq!(errors.check(Path::new("a"), Ok(1)).unwrap(), Some(1));
+        let failed: Result<u32> = Err(anyhow!(
+            "cargo metadata failed: error: line one\nline two\n"
+        ));
+        assert_eq!(
+            errors.check(Path::new("b/Cargo.toml"), failed).unwrap(),
+            None
+        );
+        assert_eq!(errors.errors.len(), 1);
+
+        let mut out = Vec::new();
+        errors.render(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\nErrors (1 project(s) skipped):\n  \
+             b/Cargo.toml: cargo metadata failed: error: line one\n    line two\n"
+        );
+        assert!(errors.finish().is_err());
+
+        // Cancellation ends the scan instead of skipping the project
+        let mut errors = ProjectErrors::new(false);
+        let interrupted: Result<u32> = Err(cancel::Cancelled::Interrupted {
+            stage: "cargo metadata".to_string(),
+        }
+        .into());
+        let error = errors.check(Path::new("c"), interrupted).unwrap_err();
+        assert_eq!(error.to_string(), "Interrupted during cargo metadata");
+        assert!(errors.errors.is_empty());
+
+        let mut errors = ProjectErrors::new(true);
+        let failed: Result<u32> = Err(anyhow!("broken"));
+        assert!(errors.check(Path::new("b"), failed).is_err());
+        assert!(errors.errors.is_empty());
+        assert!(errors.finish().is_ok());
+    }
+}
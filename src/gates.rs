@@ -0,0 +1,347 @@
+use crate::config::GateConfig;
+use crate::parse_crate_name_version;
+use crate::promote::AuditEntry;
+use crate::registry::Registry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Why an entry may not be promoted
+#[derive(Debug, Clone, PartialEq)]
+pub struct GateFailure {
+    pub crate_file: String,
+    /// "advisory", "license" or "soak"
+    pub gate: &'static str,
+    pub detail: String,
+}
+
+impl fmt::Display for GateFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]: {}", self.crate_file, self.gate, self.detail)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvisoryFile {
+    advisory: AdvisoryMetadata,
+    #[serde(default)]
+    versions: AdvisoryVersions,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvisoryMetadata {
+    id: String,
+    /// Set for notices such as "unmaintained" that are not vulnerabilities
+    informational: Option<String>,
+    /// Date the advisory was withdrawn
+    withdrawn: Option<toml::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// TOML front matter of an advisory: a ```toml fenced block in .md files, or the whole .toml file
+fn advisory_toml(content: &str) -> &str {
+    match content.strip_prefix("```toml") {
+        Some(rest) => rest.split("```").next().unwrap_or_default(),
+        None => content,
+    }
+}
+
+/// IDs of vulnerability advisories in a RustSec advisory-db checkout affecting the version
+fn advisories(db: &Path, name: &str, version: &Version) -> Result<Vec<String>> {
+    let dir = db.join("crates").join(name);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Could not read {:?}", dir)),
+    };
+
+    let mut ids = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("md" | "toml")
+        ) {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read advisory {:?}", path))?;
+        let advisory: AdvisoryFile = toml::from_str(advisory_toml(&content))
+            .with_context(|| format!("Could not parse advisory {:?}", path))?;
+        if advisory.advisory.informational.is_some() || advisory.advisory.withdrawn.is_some() {
+            continue;
+        }
+
+        let versions = advisory.versions;
+        let safe = versions
+            .patched
+            .iter()
+            .chain(&versions.unaffected)
+            .filter_map(|req| VersionReq::parse(req).ok())
+            .any(|req| req.matches(version));
+        if !safe {
+            ids.push(advisory.advisory.id);
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// Recursive-descent evaluation of an SPDX license expression against an allow-list
+struct LicenseExpr<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+    allowed: &'a [String],
+}
+
+impl LicenseExpr<'_> {
+    fn or(&mut self) -> Option<bool> {
+        let mut allowed = self.and()?;
+        while matches!(self.tokens.get(self.pos), Some(&"OR") | Some(&"/")) {
+            self.pos += 1;
+            // Evaluate both sides so the whole expression is checked for syntax
+            let right = self.and()?;
+            allowed = allowed || right;
+        }
+        Some(allowed)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut allowed = self.atom()?;
+        while self.tokens.get(self.pos) == Some(&"AND") {
+            self.pos += 1;
+            let right = self.atom()?;
+            allowed = allowed && right;
+        }
+        Some(allowed)
+    }
+
+    fn atom(&mut self) -> Option<bool> {
+        let token = *self.tokens.get(self.pos)?;
+        self.pos += 1;
+        if token == "(" {
+            let allowed = self.or()?;
+            if self.tokens.get(self.pos) != Some(&")") {
+                return None;
+            }
+            self.pos += 1;
+            return Some(allowed);
+        }
+        let allowed = self.allowed.iter().any(|license| license == token);
+        // "Apache-2.0 WITH LLVM-exception": the exception only adds permissions
+        if self.tokens.get(self.pos) == Some(&"WITH") {
+            self.pos += 2;
+        }
+        Some(allowed)
+    }
+}
+
+/// Does the license expression allow use under the allowed licenses;
+/// `None` when the expression cannot be parsed
+pub fn license_allowed(expression: &str, allowed: &[String]) -> Option<bool> {
+    let spaced = expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace('/', " / ");
+    let mut expr = LicenseExpr {
+        tokens: spaced.split_whitespace().collect(),
+        pos: 0,
+        allowed,
+    };
+    let result = expr.or()?;
+    (expr.pos == expr.tokens.len()).then_some(result)
+}
+
+/// When the entry arrived in the environment, from the newest matching audit entry
+fn arrived(audit: &[AuditEntry], crate_file: &str, env: &str) -> Option<DateTime<Utc>> {
+    audit
+        .iter()
+        .filter(|entry| entry.crate_file == crate_file && entry.to == env)
+        .filter_map(|entry| DateTime::parse_from_rfc3339(&entry.time).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .max()
+}
+
+/// Check the entries about to be promoted out of `source_env`
+pub fn check(
+    gates: &GateConfig,
+    source_env: &str,
+    source: &Registry,
+    entries: &[String],
+    audit: &[AuditEntry],
+    now: DateTime<Utc>,
+) -> Result<Vec<GateFailure>> {
+    let mut failures = Vec::new();
+    let mut fail = |crate_file: &str, gate, detail: String| {
+        failures.push(GateFailure {
+            crate_file: crate_file.to_string(),
+            gate,
+            detail,
+        })
+    };
+
+    for crate_file in entries {
+        if let Some(db) = &gates.advisory_db
+            && let Some((name, version)) = parse_crate_name_version(crate_file)
+        {
+            let ids = advisories(db, &name, &version)?;
+            if !ids.is_empty() {
+                fail(
+                    crate_file,
+                    "advisory",
+                    format!("affected by {}", ids.join(", ")),
+                );
+            }
+        }
+
+        if let Some(allowed) = &gates.allowed_licenses {
+            match source.license(crate_file) {
+                None => fail(
+                    crate_file,
+                    "license",
+                    "no license recorded in the registry".to_string(),
+                ),
+                Some(license) => match license_allowed(license, allowed) {
+                    Some(true) => {}
+                    Some(false) => {
+                        fail(crate_file, "license", format!("{} is not allowed", license))
+                    }
+                    None => fail(
+                        crate_file,
+                        "license",
+                        format!("cannot parse license expression {:?}", license),
+                    ),
+                },
+            }
+        }
+
+        if let Some(days) = gates.min_soak_days {
+            match arrived(audit, crate_file, source_env) {
+                None => fail(
+                    crate_file,
+                    "soak",
+                    format!("no audit record of when it entered {}", source_env),
+                ),
+                Some(since) if now - since < Duration::days(i64::from(days)) => fail(
+                    crate_file,
+                    "soak",
+                    format!(
+                        "in {} for {} day(s), {} required",
+                        source_env,
+                        (now - since).num_days(),
+                        days
+                    ),
+                ),
+                Some(_) => {}
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_license_allowed() {
+        let allowed = vec!["MIT".to_string(), "Apache-2.0".to_string()];
+
+        assert_eq!(license_allowed("MIT", &allowed), Some(true));
+        assert_eq!(license_allowed("MIT OR GPL-3.0", &allowed), Some(true));
+        assert_eq!(license_allowed("MIT/Apache-2.0", &allowed), Some(true));
+        assert_eq!(license_allowed("MIT AND GPL-3.0", &allowed), Some(false));
+        assert_eq!(
+            license_allowed("(MIT OR Apache-2.0) AND Unicode-3.0", &allowed),
+            Some(false)
+        );
+        assert_eq!(
+            license_allowed("Apache-2.0 WITH LLVM-exception OR GPL-3.0", &allowed),
+            Some(true)
+        );
+        assert_eq!(license_allowed("(MIT OR", &allowed), None);
+        assert_eq!(license_allowed("MIT Apache-2.0", &allowed), None);
+    }
+
+    #[test]
+    fn test_check_gates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("crates/time")).unwrap();
+        fs::write(
+            dir.join("crates/time/RUSTSEC-2020-0071.md"),
+            "```toml\n[advisory]\nid = \"RUSTSEC-2020-0071\"\npackage = \"time\"\n\n\
+             [versions]\npatched = [\">= 0.2.23\"]\nunaffected = [\"=0.1.43\"]\n```\n\n# Segfault\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("crates/time/RUSTSEC-2099-0001.md"),
+            "```toml\n[advisory]\nid = \"RUSTSEC-2099-0001\"\npackage = \"time\"\n\
+             informational = \"unmaintained\"\n```\n",
+        )
+        .unwrap();
+
+        let source = Registry::load(&{
+            let path = dir.join("dev.yaml");
+            fs::write(
+                &path,
+                "- name: time\n  version: 0.1.44\n  license: MIT OR Apache-2.0\n\
+                 - name: time\n  version: 0.3.36\n  license: MIT OR Apache-2.0\n\
+                 - name: ring\n  version: 0.17.8\n  license: ISC AND MIT\n",
+            )
+            .unwrap();
+            path
+        })
+        .unwrap();
+        let gates = GateConfig {
+            advisory_db: Some(dir.to_path_buf()),
+            allowed_licenses: Some(vec!["MIT".to_string(), "Apache-2.0".to_string()]),
+            min_soak_days: Some(7),
+        };
+        let audit_entry = |crate_file: &str, time: &str| AuditEntry {
+            time: time.to_string(),
+            action: "add".to_string(),
+            crate_file: crate_file.to_string(),
+            from: None,
+            to: "dev".to_string(),
+            user: None,
+        };
+        let audit = vec![
+            audit_entry("time-0.1.44.crate", "2026-09-01T00:00:00Z"),
+            audit_entry("time-0.3.36.crate", "2026-10-12T00:00:00Z"),
+        ];
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+
+        let entries = [
+            "time-0.1.44.crate".to_string(),
+            "time-0.3.36.crate".to_string(),
+            "ring-0.17.8.crate".to_string(),
+        ];
+        let failures = check(&gates, "dev", &source, &entries, &audit, now).unwrap();
+
+        let failures: Vec<String> = failures.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            failures,
+            vec![
+                "time-0.1.44.crate [advisory]: affected by RUSTSEC-2020-0071",
+                "time-0.3.36.crate [soak]: in dev for 3 day(s), 7 required",
+                "ring-0.17.8.crate [license]: ISC AND MIT is not allowed",
+                "ring-0.17.8.crate [soak]: no audit record of when it entered dev",
+            ]
+        );
+    }
+}
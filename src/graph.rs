@@ -0,0 +1,268 @@
+use crate::metadata::DependencyGraph;
+use crate::registry;
+use crate::report::{Report, Status};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// File format of an exported dependency graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// GraphML, for Gephi, yEd or Neo4j (apoc.import.graphml)
+    GraphMl,
+    /// A `{nodes, edges}` JSON document
+    Json,
+    /// Graphviz DOT
+    Dot,
+}
+
+impl GraphFormat {
+    /// `*.graphml`, `*.json` and `*.dot` / `*.gv` files
+    pub fn from_path(path: &Path) -> Result<GraphFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("graphml") => Ok(GraphFormat::GraphMl),
+            Some("json") => Ok(GraphFormat::Json),
+            Some("dot" | "gv") => Ok(GraphFormat::Dot),
+            _ => anyhow::bail!(
+                "Unknown graph format for {:?}: use a .graphml, .json or .dot file",
+                path
+            ),
+        }
+    }
+}
+
+/// A package in the exported graph, annotated with its check result
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphNode {
+    /// "name@version"
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    /// "member", "in-registry", "provided", "new" or "version-mismatch"
+    pub status: &'static str,
+    pub owner: Option<String>,
+}
+
+/// A normal dependency from `source` to `target` (node ids)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Graph of the packages the checked members depend on, annotated from the report
+pub fn build(graph: &DependencyGraph, member_names: &[&str], report: &Report) -> Graph {
+    let packages = graph.reachable(member_names);
+    let node_id = |name: &str, version: &semver::Version| format!("{}@{}", name, version);
+
+    let nodes = packages
+        .iter()
+        .map(|package| {
+            let missing = report
+                .missing
+                .iter()
+                .find(|m| m.name == package.name && m.version == package.version);
+            let status = if graph.is_member(&package.id) {
+                "member"
+            } else if let Some(missing) = missing {
+                match missing.status {
+                    Status::New => "new",
+                    Status::VersionMismatch => "version-mismatch",
+                }
+            } else if report.provided.iter().any(|p| p.name == package.name) {
+                "provided"
+            } else {
+                "in-registry"
+            };
+            GraphNode {
+                id: node_id(&package.name, &package.version),
+                name: package.name.clone(),
+                version: package.version.to_string(),
+                license: package.license.clone(),
+                status,
+                owner: missing.and_then(|m| m.owner.clone()),
+            }
+        })
+        .collect();
+
+    let mut edges = Vec::new();
+    for package in &packages {
+        for dep in graph.dependencies(&package.id) {
+            if let Some(target) = packages.iter().find(|p| &p.id == dep) {
+                edges.push(GraphEdge {
+                    source: node_id(&package.name, &package.version),
+                    target: node_id(&target.name, &target.version),
+                });
+            }
+        }
+    }
+    edges.sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+
+    Graph { nodes, edges }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_graphml(graph: &Graph) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+         \x20 <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"version\" for=\"node\" attr.name=\"version\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"license\" for=\"node\" attr.name=\"license\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"status\" for=\"node\" attr.name=\"status\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"owner\" for=\"node\" attr.name=\"owner\" attr.type=\"string\"/>\n\
+         \x20 <graph id=\"dependencies\" edgedefault=\"directed\">\n",
+    );
+
+    for node in &graph.nodes {
+        out.push_str(&format!("    <node id=\"{}\">\n", xml_escape(&node.id)));
+        let data = [
+            ("name", Some(node.name.as_str())),
+            ("version", Some(node.version.as_str())),
+            ("license", node.license.as_deref()),
+            ("status", Some(node.status)),
+            ("owner", node.owner.as_deref()),
+        ];
+        for (key, value) in data {
+            if let Some(value) = value {
+                out.push_str(&format!(
+                    "      <data key=\"{}\">{}</data>\n",
+                    key,
+                    xml_escape(value)
+                ));
+            }
+        }
+        out.push_str("    </node>\n");
+    }
+    for edge in &graph.edges {
+        out.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\"/>\n",
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        ));
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn render_dot(graph: &Graph) -> String {
+    let mut out = String::from("digraph dependencies {\n");
+    for node in &graph.nodes {
+        let color = match node.status {
+            "new" => "red",
+            "version-mismatch" => "orange",
+            "provided" => "gray",
+            _ => "black",
+        };
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{} {}\", color={}];\n",
+            node.id, node.name, node.version, color
+        ));
+    }
+    for edge in &graph.edges {
+        out.push_str(&format!("  \"{}\" -> \"{}\";\n", edge.source, edge.target));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Write the graph in the format matching the file extension
+pub fn write_graph(path: &Path, graph: &Graph) -> Result<()> {
+    let content = match GraphFormat::from_path(path)? {
+        GraphFormat::GraphMl => render_graphml(graph),
+        GraphFormat::Json => serde_json::to_string_pretty(graph)? + "\n",
+        GraphFormat::Dot => render_dot(graph),
+    };
+    registry::write_atomic(path, content.as_bytes())
+        .with_context(|| format!("Could not write graph to {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_graph() -> Graph {
+        Graph {
+            nodes: vec![
+                GraphNode {
+                    id: "app@0.1.0".to_string(),
+                    name: "app".to_string(),
+                    version: "0.1.0".to_string(),
+                    license: None,
+                    status: "member",
+                    owner: None,
+                },
+                GraphNode {
+                    id: "ring@0.17.8".to_string(),
+                    name: "ring".to_string(),
+                    version: "0.17.8".to_string(),
+                    license: Some("ISC AND MIT & <other>".to_string()),
+                    status: "new",
+                    owner: Some("security".to_string()),
+                },
+            ],
+            edges: vec![GraphEdge {
+                source: "app@0.1.0".to_string(),
+                target: "ring@0.17.8".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_graph_format_from_path() {
+        assert_eq!(
+            GraphFormat::from_path(Path::new("deps.graphml")).unwrap(),
+            GraphFormat::GraphMl
+        );
+        assert_eq!(
+            GraphFormat::from_path(Path::new("deps.json")).unwrap(),
+            GraphFormat::Json
+        );
+        assert_eq!(
+            GraphFormat::from_path(Path::new("deps.gv")).unwrap(),
+            GraphFormat::Dot
+        );
+        assert!(GraphFormat::from_path(Path::new("deps.txt")).is_err());
+    }
+
+    #[test]
+    fn test_render_graphml() {
+        let graphml = render_graphml(&sample_graph());
+        assert!(graphml.contains("<graph id=\"dependencies\" edgedefault=\"directed\">"));
+        assert!(
+            graphml
+                .contains("    <node id=\"ring@0.17.8\">\n      <data key=\"name\">ring</data>\n")
+        );
+        assert!(graphml.contains("<data key=\"license\">ISC AND MIT &amp; &lt;other&gt;</data>"));
+        assert!(graphml.contains("<data key=\"owner\">security</data>"));
+        assert!(graphml.contains("<edge source=\"app@0.1.0\" target=\"ring@0.17.8\"/>"));
+        // Absent values are left out
+        assert_eq!(graphml.matches("<data key=\"license\">").count(), 1);
+    }
+
+    #[test]
+    fn test_render_json_and_dot() {
+        let json = serde_json::to_value(sample_graph()).unwrap();
+        assert_eq!(json["nodes"][1]["status"], "new");
+        assert_eq!(json["edges"][0]["source"], "app@0.1.0");
+
+        let dot = render_dot(&sample_graph());
+        assert!(dot.contains("\"ring@0.17.8\" [label=\"ring 0.17.8\", color=red];"));
+        assert!(dot.contains("\"app@0.1.0\" -> \"ring@0.17.8\";"));
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt::Display;
+
+/// Language of the human-readable report
+///
+/// Only the console text is translated: JSON, YAML and CSV field names and values stay in
+/// English so tooling reading them is unaffected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// German
+    De,
+    /// French
+    Fr,
+}
+
+/// A translatable text of the human-readable report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    ProvidedHeader,
+    IgnoredHeader,
+    IgnoredUntil,
+    AllPresent,
+    AllOtherPresent,
+    FoundMissing,
+    MismatchTag,
+    NewTag,
+    Owner,
+    Mirror,
+    IgnoreExpired,
+    DeclaredInAs,
+    DeclaredIn,
+    IntroducedBy,
+    MoreNotShown,
+    RequireApproval,
+    NoCompatible,
+    TyposquatHeader,
+    Typosquat,
+    ApprovalHeader,
+    Superseded,
+    Severity,
+    FixitHeader,
+    FixitRun,
+    UnownedHeader,
+    ReasonNew,
+    ReasonMismatch,
+}
+
+impl Lang {
+    /// Text of a message with its `{name}` placeholders filled in
+    ///
+    /// The template is scanned once, so a value that contains a placeholder is inserted as is.
+    pub fn format(self, msg: Msg, args: &[(&str, &dyn Display)]) -> String {
+        let mut rest = self.text(msg);
+        let mut text = String::new();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            text.push_str(&rest[..start]);
+            let placeholder = &rest[start..start + len + 1];
+            let name = &placeholder[1..placeholder.len() - 1];
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => text.push_str(&value.to_string()),
+                None => text.push_str(placeholder),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        text.push_str(rest);
+        text
+    }
+
+    /// Text of a message, placeholders left as they are
+    pub fn text(self, msg: Msg) -> &'static str {
+        match self {
+            Lang::En => en(msg),
+            Lang::De => de(msg),
+            Lang::Fr => fr(msg),
+        }
+    }
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::ProvidedHeader => "Provided by toolchain (not required from the registry):",
+        Msg::IgnoredHeader => "Ignored by config:",
+        Msg::IgnoredUntil => "{reason}; until {until}",
+        Msg::AllPresent => "All dependencies from cargo tree are in the offline registry.",
+        Msg::AllOtherPresent => {
+            "All other dependencies from cargo tree are in the offline registry."
+        }
+        Msg::FoundMissing => "Found {count} dependencies missing from the offline registry:",
+        Msg::MismatchTag => {
+            "[requirement: \"{requirement}\", registry has: {versions}; no compatible version]"
+        }
+        Msg::NewTag => "[WARNING: NEW dependency, requires approval]",
+        Msg::Owner => "owner: {owner}",
+        Msg::Mirror => "mirror: {url}",
+        Msg::IgnoreExpired => "ignore expired on {until} ({reason})",
+        Msg::DeclaredInAs => "declared in {location} as \"{requirement}\"",
+        Msg::DeclaredIn => "declared in {location}",
+        Msg::IntroducedBy => "introduced by {crates}",
+        Msg::MoreNotShown => {
+            "... {count} more not shown (see --limit/--only, or --output json/csv/yaml for the full list)"
+        }
+        Msg::RequireApproval => "{count} crate(s) require approval:",
+        Msg::NoCompatible => "(no compatible version found in registry)",
+        Msg::TyposquatHeader => "!!! POSSIBLE TYPOSQUATTING, verify these crates before approving:",
+        Msg::Typosquat => "POSSIBLE TYPOSQUATTING: {signals}",
+        Msg::ApprovalHeader => "CRATES REQUIRING APPROVAL:",
+        Msg::Superseded => "superseded by {replacement}, migrate instead of approving",
+        Msg::Severity => "Severity: {errors} error(s), {warnings} warning(s), {info} info",
+        Msg::FixitHeader => {
+            "FIX-IT: these crates are only missing because the lockfile is ahead of the registry."
+        }
+        Msg::FixitRun => "Run these commands to resolve to versions already in the registry:",
+        Msg::UnownedHeader => "UNOWNED CRATES (no matching ownership rule):",
+        Msg::ReasonNew => "new dependency",
+        Msg::ReasonMismatch => {
+            "requirement \"{requirement}\" not satisfied by registry versions [{versions}]"
+        }
+    }
+}
+
+fn de(msg: Msg) -> &'static str {
+    match msg {
+        Msg::ProvidedHeader => {
+            "Von der Toolchain bereitgestellt (nicht aus der Registry benötigt):"
+        }
+        Msg::IgnoredHeader => "Durch die Konfiguration ignoriert:",
+        Msg::IgnoredUntil => "{reason}; bis {until}",
+        Msg::AllPresent => "Alle Abhängigkeiten aus cargo tree sind in der Offline-Registry.",
+        Msg::AllOtherPresent => {
+            "Alle anderen Abhängigkeiten aus cargo tree sind in der Offline-Registry."
+        }
+        Msg::FoundMissing => "{count} Abhängigkeiten fehlen in der Offline-Registry:",
+        Msg::MismatchTag => {
+            "[Anforderung: \"{requirement}\", Registry enthält: {versions}; keine kompatible Version]"
+        }
+        Msg::NewTag => "[WARNUNG: NEUE Abhängigkeit, Freigabe erforderlich]",
+        Msg::Owner => "zuständig: {owner}",
+        Msg::Mirror => "Mirror: {url}",
+        Msg::IgnoreExpired => "Ausnahme abgelaufen am {until} ({reason})",
+        Msg::DeclaredInAs => "deklariert in {location} als \"{requirement}\"",
+        Msg::DeclaredIn => "deklariert in {location}",
+        Msg::IntroducedBy => "eingebracht durch {crates}",
+        Msg::MoreNotShown => {
+            "... {count} weitere nicht angezeigt (siehe --limit/--only, oder --output json/csv/yaml für die vollständige Liste)"
+        }
+        Msg::RequireApproval => "{count} Crate(s) benötigen eine Freigabe:",
+        Msg::NoCompatible => "(keine kompatible Version in der Registry gefunden)",
+        Msg::TyposquatHeader => {
+            "!!! MÖGLICHES TYPOSQUATTING, diese Crates vor der Freigabe prüfen:"
+        }
+        Msg::Typosquat => "MÖGLICHES TYPOSQUATTING: {signals}",
+        Msg::ApprovalHeader => "FREIGABEPFLICHTIGE CRATES:",
+        Msg::Superseded => "ersetzt durch {replacement}, migrieren statt freigeben",
+        Msg::Severity => "Schweregrad: {errors} Fehler, {warnings} Warnung(en), {info} Info",
+        Msg::FixitHeader => {
+            "FIX-IT: diese Crates fehlen nur, weil die Lockfile neuer als die Registry ist."
+        }
+        Msg::FixitRun => {
+            "Diese Befehle stellen auf Versionen um, die bereits in der Registry sind:"
+        }
+        Msg::UnownedHeader => "CRATES OHNE ZUSTÄNDIGE (keine passende Zuständigkeitsregel):",
+        Msg::ReasonNew => "neue Abhängigkeit",
+        Msg::ReasonMismatch => {
+            "Anforderung \"{requirement}\" von den Registry-Versionen [{versions}] nicht erfüllt"
+        }
+    }
+}
+
+fn fr(msg: Msg) -> &'static str {
+    match msg {
+        Msg::ProvidedHeader => "Fourni par la toolchain (non requis dans le registre) :",
+        Msg::IgnoredHeader => "Ignoré par la configuration :",
+        Msg::IgnoredUntil => "{reason} ; jusqu'au {until}",
+        Msg::AllPresent => "Toutes les dépendances de cargo tree sont dans le registre hors ligne.",
+        Msg::AllOtherPresent => {
+            "Toutes les autres dépendances de cargo tree sont dans le registre hors ligne."
+        }
+        Msg::FoundMissing => "{count} dépendances manquent dans le registre hors ligne :",
+        Msg::MismatchTag => {
+            "[exigence : \"{requirement}\", le registre contient : {versions} ; aucune version compatible]"
+        }
+        Msg::NewTag => "[ATTENTION : NOUVELLE dépendance, approbation requise]",
+        Msg::Owner => "responsable : {owner}",
+        Msg::Mirror => "miroir : {url}",
+        Msg::IgnoreExpired => "exception expirée le {until} ({reason})",
+        Msg::DeclaredInAs => "déclaré dans {location} comme \"{requirement}\"",
+        Msg::DeclaredIn => "déclaré dans {location}",
+        Msg::IntroducedBy => "introduit par {crates}",
+        Msg::MoreNotShown => {
+            "... {count} de plus non affichés (voir --limit/--only, ou --output json/csv/yaml pour la liste complète)"
+        }
+        Msg::RequireApproval => "{count} crate(s) nécessitent une approbation :",
+        Msg::NoCompatible => "(aucune version compatible trouvée dans le registre)",
+        Msg::TyposquatHeader => {
+            "!!! TYPOSQUATTING POSSIBLE, vérifiez ces crates avant de les approuver :"
+        }
+        Msg::Typosquat => "TYPOSQUATTING POSSIBLE : {signals}",
+        Msg::ApprovalHeader => "CRATES NÉCESSITANT UNE APPROBATION :",
+        Msg::Superseded => "remplacé par {replacement}, migrer au lieu d'approuver",
+        Msg::Severity => "Gravité : {errors} erreur(s), {warnings} avertissement(s), {info} info",
+        Msg::FixitHeader => {
+            "FIX-IT : ces crates manquent uniquement parce que le lockfile est en avance sur le registre."
+        }
+        Msg::FixitRun => {
+            "Exécutez ces commandes pour revenir à des versions déjà présentes dans le registre :"
+        }
+        Msg::UnownedHeader => "CRATES SANS RESPONSABLE (aucune règle de propriété ne correspond) :",
+        Msg::ReasonNew => "nouvelle dépendance",
+        Msg::ReasonMismatch => {
+            "exigence \"{requirement}\" non satisfaite par les versions du registre [{versions}]"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+    use std::collections::BTreeSet;
+
+    const ALL: [Msg; 27] = [
+        Msg::ProvidedHeader,
+        Msg::IgnoredHeader,
+        Msg::IgnoredUntil,
+        Msg::AllPresent,
+        Msg::AllOtherPresent,
+        Msg::FoundMissing,
+        Msg::MismatchTag,
+        Msg::NewTag,
+        Msg::Owner,
+        Msg::Mirror,
+        Msg::IgnoreExpired,
+        Msg::DeclaredInAs,
+        Msg::DeclaredIn,
+        Msg::IntroducedBy,
+        Msg::MoreNotShown,
+        Msg::RequireApproval,
+        Msg::NoCompatible,
+        Msg::TyposquatHeader,
+        Msg::Typosquat,
+        Msg::ApprovalHeader,
+        Msg::Superseded,
+        Msg::Severity,
+        Msg::FixitHeader,
+        Msg::FixitRun,
+        Msg::UnownedHeader,
+        Msg::ReasonNew,
+        Msg::ReasonMismatch,
+    ];
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_catalogs_agree() {
+        // A translation using other placeholders would print them unfilled
+        for msg in ALL {
+            let english = placeholders(Lang::En.text(msg));
+            for lang in Lang::value_variants() {
+                assert_eq!(
+                    placeholders(lang.text(msg)),
+                    english,
+                    "{:?} in {:?}",
+                    msg,
+                    lang
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            Lang::En.format(Msg::RequireApproval, &[("count", &3)]),
+            "3 crate(s) require approval:"
+        );
+        assert_eq!(
+            Lang::De.format(
+                Msg::Severity,
+                &[("errors", &2), ("warnings", &1), ("info", &0)]
+            ),
+            "Schweregrad: 2 Fehler, 1 Warnung(en), 0 Info"
+        );
+        // Values are never substituted again
+        assert_eq!(
+            Lang::En.format(
+                Msg::IgnoredUntil,
+                &[("reason", &"waiting for {until}"), ("until", &"2026-12-31")]
+            ),
+            "waiting for {until}; until 2026-12-31"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::config::IgnoreEntry;
+use crate::report::{IgnoreNote, IgnoredCrate, MissingCrate, Severity};
+use chrono::NaiveDate;
+
+fn note(entry: &IgnoreEntry) -> IgnoreNote {
+    IgnoreNote {
+        reason: entry.reason.clone(),
+        until: entry.until.map(|until| until.to_string()),
+    }
+}
+
+/// Split off the missing crates ignored by the config
+///
+/// The first matching entry applies. Crates whose entry has expired stay findings,
+/// marked with the expired entry so temporary exceptions cannot silently become permanent.
+pub fn apply(
+    missing: Vec<MissingCrate>,
+    entries: &[IgnoreEntry],
+    today: NaiveDate,
+) -> (Vec<MissingCrate>, Vec<IgnoredCrate>) {
+    let mut kept = Vec::new();
+    let mut ignored = Vec::new();
+    for mut missing in missing {
+        match entries
+            .iter()
+            .find(|entry| entry.matches(&missing.name, &missing.version))
+        {
+            Some(entry) if entry.is_expired(today) => {
+                missing.expired_ignore = Some(note(entry));
+                kept.push(missing);
+            }
+            Some(entry) => ignored.push(IgnoredCrate {
+                name: missing.name,
+                version: missing.version,
+                ignore: note(entry),
+                severity: Severity::Info,
+            }),
+            None => kept.push(missing),
+        }
+    }
+    (kept, ignored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::report::Status;
+    use semver::Version;
+
+    fn missing(name: &str, version: &str) -> MissingCrate {
+        MissingCrate {
+            name: name.to_string(),
+            version: Version::parse(version).unwrap(),
+            crate_file: format!("{}-{}.crate", name, version),
+            requirement: format!("^{}", version),
+            canonical_requirement: crate::manifest::canonical(
+                &semver::VersionReq::parse(&format!("^{}", version)).unwrap(),
+            ),
+            declared_requirement: None,
+            status: Status::New,
+            registry_versions: vec![],
+            reason: "new dependency".to_string(),
+            severity: Severity::Error,
+            owner: None,
+            declared_in: None,
+            compatible_fallback: None,
+            introduced_by: vec![],
+            license: None,
+            superseded_by: None,
+            download_url: None,
+            mirror_url: None,
+            suspicious: Vec::new(),
+            expired_ignore: None,
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let config: Config = toml::from_str(
+            r#"
+            ignore = [
+                { crate = "foo", until = "2025-12-31", reason = "ticket-123" },
+                { crate = "bar", until = "2026-12-31", reason = "ticket-456" },
+                { crate = "baz", version = "<2", reason = "vendored" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let today = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+
+        let (kept, ignored) = apply(
+            vec![
+                missing("bar", "1.0.0"),
+                missing("baz", "1.5.0"),
+                missing("baz", "2.0.0"),
+                missing("foo", "0.3.0"),
+            ],
+            &config.ignore,
+            today,
+        );
+
+        let kept: Vec<(&str, Option<&IgnoreNote>)> = kept
+            .iter()
+            .map(|m| (m.crate_file.as_str(), m.expired_ignore.as_ref()))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                ("baz-2.0.0.crate", None),
+                (
+                    "foo-0.3.0.crate",
+                    Some(&IgnoreNote {
+                        reason: "ticket-123".to_string(),
+                        until: Some("2025-12-31".to_string())
+                    })
+                ),
+            ]
+        );
+        let ignored: Vec<(&str, Option<&str>)> = ignored
+            .iter()
+            .map(|i| (i.name.as_str(), i.ignore.until.as_deref()))
+            .collect();
+        assert_eq!(ignored, vec![("bar", Some("2026-12-31")), ("baz", None)]);
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One published version of a crate, as listed in the index
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexVersion {
+    pub vers: Version,
+    #[serde(default)]
+    pub yanked: bool,
+    #[serde(default)]
+    pub deps: Vec<IndexDependency>,
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Features using `dep:` or `?/` syntax, split off for older cargo versions
+    #[serde(default)]
+    pub features2: BTreeMap<String, Vec<String>>,
+}
+
+impl IndexVersion {
+    /// What a feature enables, from either feature table
+    pub fn feature(&self, name: &str) -> Option<&[String]> {
+        self.features
+            .get(name)
+            .or_else(|| self.features2.get(name))
+            .map(Vec::as_slice)
+    }
+}
+
+/// A dependency declared by a published version
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexDependency {
+    /// Name in the dependent's manifest; the crate itself when `package` is absent
+    pub name: String,
+    /// Requirement as published; parsed on use so one odd entry does not hide the version
+    pub req: String,
+    /// "normal", "build" or "dev" (absent means normal)
+    pub kind: Option<String>,
+    /// Actual crate name of a renamed dependency
+    pub package: Option<String>,
+    /// Only built when a feature enables it
+    #[serde(default)]
+    pub optional: bool,
+    /// Features the dependent enables on it
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default = "default_true")]
+    pub default_features: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl IndexDependency {
+    /// Name of the crate this dependency resolves to
+    pub fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Dev-dependencies are never needed to build a dependent
+    pub fn is_dev(&self) -> bool {
+        self.kind.as_deref() == Some("dev")
+    }
+}
+
+/// Path of a crate's file relative to the index root, following the crates.io layout
+/// (`1/a`, `2/ab`, `3/a/abc`, `se/rd/serde`)
+///
+/// Fails for names cargo would not accept, which have no place in the index.
+pub fn crate_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "Invalid crate name {:?}: only ASCII letters, digits, '-' and '_' are allowed",
+            name
+        );
+    }
+    let name = name.to_ascii_lowercase();
+    Ok(match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+    })
+}
+
+/// A local snapshot (checkout) of a crates.io-style registry index
+#[derive(Debug)]
+pub struct Index {
+    root: PathBuf,
+}
+
+impl Index {
+    pub fn open(root: &Path) -> Result<Index> {
+        if !root.is_dir() {
+            anyhow::bail!("Index snapshot {:?} is not a directory", root);
+        }
+        Ok(Index {
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// Published versions of a crate; empty when the index does not know the crate
+    pub fn versions(&self, name: &str) -> Result<Vec<IndexVersion>> {
+        let path = self.root.join(crate_path(name)?);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Could not read index file {:?}", path));
+            }
+        };
+        Ok(parse_versions(&content))
+    }
+}
+
+/// Parse an index file (one JSON object per line), skipping lines that do not parse
+fn parse_versions(content: &str) -> Vec<IndexVersion> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Newest stable version that has not been yanked
+pub fn latest(versions: &[IndexVersion]) -> Option<&IndexVersion> {
+    versions
+        .iter()
+        .filter(|v| !v.yanked && v.vers.pre.is_empty())
+        .max_by(|a, b| a.vers.cmp(&b.vers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crate_path() {
+        let path = |name: &str| crate_path(name).unwrap();
+        assert_eq!(path("a"), PathBuf::from("1/a"));
+        assert_eq!(path("cc"), PathBuf::from("2/cc"));
+        assert_eq!(path("syn"), PathBuf::from("3/s/syn"));
+        assert_eq!(path("serde"), PathBuf::from("se/rd/serde"));
+        assert_eq!(path("Inflector"), PathBuf::from("in/fl/inflector"));
+
+        // Never sliced inside a multi-byte character
+        assert!(crate_path("ñandu").is_err());
+        assert!(crate_path("").is_err());
+        assert!(crate_path("../etc").is_err());
+    }
+
+    #[test]
+    fn test_latest() {
+        let versions = parse_versions(
+            r#"{"name":"serde","vers":"1.0.200","deps":[],"yanked":false}
+{"name":"serde","vers":"1.0.228","deps":[{"name":"derive","package":"serde_derive","req":"=1.0.228","kind":"normal"},{"name":"serde_json","req":"^1","kind":"dev"}],"yanked":false}
+{"name":"serde","vers":"1.0.229","yanked":true}
+{"name":"serde","vers":"2.0.0-alpha.1","yanked":false}
+not json
+"#,
+        );
+        assert_eq!(versions.len(), 4);
+        assert_eq!(
+            latest(&versions).map(|v| &v.vers),
+            Some(&Version::parse("1.0.228").unwrap())
+        );
+        assert!(latest(&[]).is_none());
+
+        let deps = &latest(&versions).unwrap().deps;
+        assert_eq!(deps[0].crate_name(), "serde_derive");
+        assert!(!deps[0].is_dev());
+        assert!(deps[1].is_dev());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::i18n::Lang;
+use std::io::{self, IsTerminal, Write};
+
+/// How the human-readable report is laid out on the terminal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layout {
+    /// Wrap prose lines at this many columns; None leaves every line whole
+    pub width: Option<usize>,
+    /// One line per finding, without the repeated approval list
+    pub compact: bool,
+    /// Language of the report text
+    pub lang: Lang,
+}
+
+impl Layout {
+    /// Width from `--width`, else `COLUMNS`, else the terminal stdout is attached to;
+    /// None with `no_wrap` or when stdout is not a terminal
+    pub fn new(width: Option<usize>, no_wrap: bool, compact: bool, lang: Lang) -> Layout {
+        let width = if no_wrap {
+            None
+        } else {
+            width
+                .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+                .or_else(terminal_width)
+        };
+        Layout {
+            width,
+            compact,
+            lang,
+        }
+    }
+}
+
+/// Columns of the terminal stdout is attached to
+fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+        (ok && size.ws_col > 0).then_some(usize::from(size.ws_col))
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+/// Extra indentation of continuation lines, so a wrapped entry stays visibly one entry
+const HANG: usize = 4;
+
+/// Split a line at spaces so no piece exceeds `width` columns, where possible
+///
+/// Continuation lines keep the line's own indentation plus `HANG`; words longer than the
+/// width (URLs, paths) are never broken.
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    let text = line.trim_start_matches(' ');
+    let indent = line.len() - text.len();
+    let mut lines = Vec::new();
+    let mut current = " ".repeat(indent);
+    let mut current_width = indent;
+    let mut empty = true;
+    for word in text.split(' ') {
+        let word_width = word.chars().count();
+        if !empty && current_width + 1 + word_width > width {
+            lines.push(std::mem::replace(&mut current, " ".repeat(indent + HANG)));
+            current_width = indent + HANG;
+            empty = true;
+        }
+        if !empty {
+            current.push(' ');
+            current_width += 1;
+        }
+        current.push_str(word);
+        current_width += word_width;
+        empty = false;
+    }
+    lines.push(current);
+    lines
+}
+
+/// Writes report lines according to a layout
+pub struct Renderer<'a, W: Write> {
+    out: &'a mut W,
+    layout: Layout,
+}
+
+impl<'a, W: Write> Renderer<'a, W> {
+    pub fn new(out: &'a mut W, layout: Layout) -> Renderer<'a, W> {
+        Renderer { out, layout }
+    }
+
+    pub fn compact(&self) -> bool {
+        self.layout.compact
+    }
+
+    pub fn lang(&self) -> Lang {
+        self.layout.lang
+    }
+
+    /// A line of prose, wrapped to the layout width
+    pub fn line(&mut self, text: impl AsRef<str>) -> io::Result<()> {
+        let text = text.as_ref();
+        match self.layout.width {
+            Some(width) => {
+                for line in wrap(text, width) {
+                    writeln!(self.out, "{}", line)?;
+                }
+                Ok(())
+            }
+            None => writeln!(self.out, "{}", text),
+        }
+    }
+
+    /// A line that must stay whole, e.g. a command to copy
+    pub fn verbatim(&mut self, text: impl AsRef<str>) -> io::Result<()> {
+        writeln!(self.out, "{}", text.as_ref())
+    }
+
+    pub fn blank(&mut self) -> io::Result<()> {
+        writeln!(self.out)
+    }
+
+    /// Horizontal rule framing a section, no wider than the layout
+    pub fn rule(&mut self) -> io::Result<()> {
+        let width = self.layout.width.map_or(40, |width| width.clamp(1, 40));
+        writeln!(self.out, "{}", "=".repeat(width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("  - serde-1.0.228.crate (new dependency)", 24),
+            vec!["  - serde-1.0.228.crate", "      (new dependency)"]
+        );
+        assert_eq!(wrap("short line", 80), vec!["short line"]);
+        // Long words overflow instead of being cut
+        assert_eq!(
+            wrap(
+                "  see https://static.crates.io/crates/serde/serde-1.0.228.crate",
+                20
+            ),
+            vec![
+                "  see",
+                "      https://static.crates.io/crates/serde/serde-1.0.228.crate"
+            ]
+        );
+        assert_eq!(wrap("", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_renderer() {
+        let mut out = Vec::new();
+        let mut renderer = Renderer::new(
+            &mut out,
+            Layout {
+                width: Some(12),
+                ..Layout::default()
+            },
+        );
+        renderer.line("one two three four").unwrap();
+        renderer
+            .verbatim("cargo update -p serde@1.0.228 --precise 1.0.200")
+            .unwrap();
+        renderer.rule().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "one two\n    three\n    four\n\
+             cargo update -p serde@1.0.228 --precise 1.0.200\n\
+             ============\n"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::cancel;
+use crate::cargo;
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Lockfile of the workspace whose root manifest is given
+pub fn lockfile_path(root_manifest: &Path) -> PathBuf {
+    root_manifest.with_file_name("Cargo.lock")
+}
+
+/// Whether cargo's error output says the lockfile is out of date with the manifests
+fn is_drift_error(stderr: &str) -> bool {
+    // "the lock file ... needs to be updated but --locked was passed" (older cargo) or
+    // "cannot update the lock file ... because --locked was passed" (newer cargo)
+    stderr.contains("lock file") && stderr.contains("--locked was passed")
+}
+
+/// Detect Cargo.lock being stale relative to the manifests
+///
+/// Resolving with `--locked` fails instead of updating the lockfile when they have
+/// drifted apart. Checking against a drifted lockfile would report whatever cargo
+/// resolves on the fly rather than what the project actually builds with.
+pub fn check_drift(manifest_path: &Path, lockfile: &Path) -> Result<()> {
+    if !lockfile.exists() {
+        // Nothing to drift from; cargo generates a fresh lockfile
+        return Ok(());
+    }
+
+    let output = cancel::output(
+        cargo::command("metadata")
+            .arg("--manifest-path")
+            .arg(manifest_path)
+            .arg("--format-version")
+            .arg("1")
+            .arg("--locked"),
+        "cargo metadata",
+    )?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if is_drift_error(&stderr) {
+        anyhow::bail!(
+            "Lockfile drift: {} is out of date with the Cargo.toml manifests.\n\
+             Results would not reflect what the project builds with; run `cargo update --workspace` \
+             (or `cargo generate-lockfile`) and commit the lockfile, then check again.",
+            lockfile.display()
+        );
+    }
+    anyhow::bail!("cargo metadata failed: {}", stderr);
+}
+
+/// Removes a lockfile cargo generates during the check, so a project without one is left as it was
+///
+/// An existing lockfile is protected by `--locked` instead.
+#[derive(Debug)]
+pub struct GeneratedGuard {
+    /// Lockfile to remove on drop; `None` when it existed beforehand
+    generated: Option<PathBuf>,
+}
+
+impl GeneratedGuard {
+    pub fn new(lockfile: &Path) -> GeneratedGuard {
+        GeneratedGuard {
+            generated: (!lockfile.exists()).then(|| lockfile.to_path_buf()),
+        }
+    }
+}
+
+impl Drop for GeneratedGuard {
+    fn drop(&mut self) {
+        if let Some(lockfile) = &self.generated
+            && lockfile.exists()
+        {
+            match fs::remove_file(lockfile) {
+                Ok(()) => eprintln!("Removed {:?} generated while resolving", lockfile),
+                Err(err) => eprintln!(
+                    "Warning: could not remove generated {:?}: {}",
+                    lockfile, err
+                ),
+            }
+        }
+    }
+}
+
+/// A `[[package]]` entry of a Cargo.lock
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    /// "registry+...", "sparse+..." or "git+..."; absent for path and workspace packages
+    pub source: Option<String>,
+    pub checksum: Option<String>,
+    /// "name", or "name version [(source)]" when the lockfile has several versions of the crate
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Downloaded from a registry, as opposed to git or path dependencies
+    pub fn is_from_registry(&self) -> bool {
+        self.source
+            .as_deref()
+            .is_some_and(|source| source.starts_with("registry+") || source.starts_with("sparse+"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// Packages recorded in a Cargo.lock
+pub fn packages(lockfile: &Path) -> Result<Vec<LockedPackage>> {
+    let content = fs::read_to_string(lockfile)
+        .with_context(|| format!("Could not read lockfile {:?}", lockfile))?;
+    let parsed: Lockfile = toml::from_str(&content)
+        .with_context(|| format!("Could not parse lockfile {:?}", lockfile))?;
+    Ok(parsed.package)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_drift_error() {
+        assert!(is_drift_error(
+            "error: the lock file /ws/Cargo.lock needs to be updated but --locked was passed to prevent this\n\
+             If you want to try to generate the lock file without accessing the network, remove the --locked flag and use --offline instead."
+        ));
+        assert!(is_drift_error(
+            "error: cannot update the lock file /ws/Cargo.lock because --locked was passed to prevent this"
+        ));
+        assert!(!is_drift_error(
+            "error: failed to parse manifest at `/ws/Cargo.toml`"
+        ));
+    }
+
+    #[test]
+    fn test_generated_guard() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let lockfile = dir.join("Cargo.lock");
+
+        {
+            let _guard = GeneratedGuard::new(&lockfile);
+            fs::write(&lockfile, "version = 4\n").unwrap();
+        }
+        assert!(!lockfile.exists());
+
+        fs::write(&lockfile, "version = 4\n").unwrap();
+        drop(GeneratedGuard::new(&lockfile));
+        assert!(lockfile.exists());
+    }
+
+    #[test]
+    fn test_lockfile_path() {
+        assert_eq!(
+            lockfile_path(Path::new("/ws/Cargo.toml")),
+            PathBuf::from("/ws/Cargo.lock")
+        );
+    }
+}
@@ -1,70 +1,489 @@
+mod archive;
+mod cancel;
+mod cargo;
+mod closure;
+mod compare;
+mod config;
+mod features;
+mod fingerprint;
+mod fleet;
+mod gates;
+mod graph;
+mod i18n;
+mod ignore;
+mod index;
+mod layout;
+mod lockfile;
+mod majors;
+mod man;
+mod manifest;
+mod merge;
+mod metadata;
+mod outdated;
+mod owners;
+mod parallel;
+mod pending;
+mod plan;
+mod platform;
+mod plugin;
+mod promote;
+mod query;
+mod registry;
+mod report;
+mod seed;
+mod self_check;
+mod ticket;
+mod toolchain;
+mod trace;
+mod trend;
+mod typosquat;
+mod verify;
+mod workspace;
+
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use config::Config;
+use fingerprint::Fingerprint;
+use manifest::Requirement;
+use metadata::DependencyGraph;
+use owners::Owners;
+use platform::Targets;
+use registry::Registry;
+use report::{MissingCrate, ProvidedCrate, Report, SCHEMA_VERSION, Severity, Status};
 use semver::{Version, VersionReq};
-use std::collections::{HashMap, HashSet};
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
 use std::path::PathBuf;
-use std::process::Command;
-use toml::Value;
+use toolchain::Provided;
+use workspace::{Selection, Workspace};
 
 #[derive(Parser, Debug)]
 #[command(
     author,
     version,
-    about = "Finds missing dependencies for offline registry"
+    about = "Finds missing dependencies for offline registry",
+    subcommand_negates_reqs = true
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Path to the Cargo.toml of the project you want to check
     #[arg(short, long, default_value = "./Cargo.toml")]
     manifest_path: PathBuf,
 
-    /// Path to the text file listing your current offline registry crates
-    #[arg(short, long)]
-    registry_file: PathBuf,
+    /// Path to the file listing your current offline registry crates
+    /// (text, or YAML with name/version/checksum entries when named *.yaml / *.yml);
+    /// "-" reads a text listing from stdin, and --write then prints the updated listing
+    #[arg(short, long, required_unless_present = "env", conflicts_with = "env")]
+    registry_file: Option<PathBuf>,
+
+    /// Check against the registry of this environment from the config file
+    /// ([environments.<NAME>]) instead of --registry-file
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
+
+    /// Check all workspace members instead of the default members (like cargo --workspace)
+    #[arg(long)]
+    workspace: bool,
+
+    /// Skip a workspace member, e.g. examples or fuzz targets (requires --workspace; repeatable)
+    #[arg(long, value_name = "MEMBER", requires = "workspace")]
+    exclude: Vec<String>,
+
+    /// Check dependencies for this target triple instead of the host, or "all"
+    /// (like cargo tree --target; repeatable)
+    #[arg(long = "target", value_name = "TRIPLE")]
+    targets: Vec<String>,
+
+    /// Crate provided by the toolchain or patched internally, never required from the
+    /// registry (wildcards allowed; repeatable)
+    #[arg(long = "provided", value_name = "CRATE")]
+    provided: Vec<String>,
 
     /// Add missing crates and sort the file
     #[arg(short, long)]
     write: bool,
+
+    /// Add missing crates to this pending-approval listing instead of the registry;
+    /// approved entries are merged with apply-pending
+    #[arg(long, value_name = "PATH", conflicts_with = "write")]
+    write_pending: Option<PathBuf>,
+
+    /// Executable to run with the JSON report on stdin; a non-zero exit fails the check
+    /// (repeatable)
+    #[arg(long = "plugin", value_name = "PATH")]
+    plugins: Vec<PathBuf>,
+
+    /// Path to a TOML config file (ticket template, distribution URL, ...)
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Run cargo with net.offline=true so resolving never touches the network
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Stop any single cargo invocation or network call after this many seconds
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
+    /// Run per-crate steps (enrichment, verify downloads) on at most N threads
+    /// (default: number of CPUs)
+    #[arg(short, long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+
+    /// Send timing spans (resolution, registry parsing, downloads, policy checks) to this
+    /// OTLP/HTTP collector, e.g. http://localhost:4318 (default: $OTEL_EXPORTER_OTLP_ENDPOINT)
+    #[arg(long, global = true, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+
+    /// File an issue listing the crates requiring approval, using the [ticket] template
+    /// from the config file
+    #[arg(long, requires = "config")]
+    create_ticket: bool,
+
+    /// Print the crates.io download URL (and the mirror URL from the config) of each
+    /// missing crate
+    #[arg(long)]
+    show_urls: bool,
+
+    /// Flag new crates whose names resemble registry or popular crates, or that crates.io
+    /// reports as recently published or barely downloaded
+    #[arg(long)]
+    typosquat: bool,
+
+    /// Popular crate names (one per line) --typosquat compares against, besides the registry
+    #[arg(long, value_name = "PATH", requires = "typosquat")]
+    popular_crates: Option<PathBuf>,
+
+    /// CODEOWNERS-style file mapping crate name patterns to owning teams
+    #[arg(long, value_name = "PATH")]
+    owners: Option<PathBuf>,
+
+    /// Archive each run's full report (JSON and text) in a timestamped directory here,
+    /// keeping a `latest` symlink to the most recent run
+    #[arg(long, value_name = "PATH")]
+    report_dir: Option<PathBuf>,
+
+    /// Export the annotated dependency graph; the format follows the extension:
+    /// .graphml (Gephi, Neo4j), .json (nodes/edges) or .dot
+    #[arg(long, value_name = "PATH")]
+    graph: Option<PathBuf>,
+
+    /// Report format written to stdout; progress messages go to stderr
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
+    /// List at most N missing crates in the human-readable output; other formats
+    /// and archived reports always contain every crate
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// List only missing crates with this status in the human-readable output
+    #[arg(long, value_enum, value_name = "STATUS")]
+    only: Option<report::Status>,
+
+    /// Wrap the human-readable output at N columns (default: COLUMNS, or the terminal width)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(20..))]
+    width: Option<u16>,
+
+    /// Never wrap the human-readable output
+    #[arg(long, conflicts_with = "width")]
+    no_wrap: bool,
+
+    /// One line per missing crate in the human-readable output, without the approval list
+    #[arg(long)]
+    compact: bool,
+
+    /// Language of the human-readable output (other formats stay in English)
+    #[arg(long, value_enum, value_name = "LANG", default_value_t = i18n::Lang::En)]
+    lang: i18n::Lang,
+
+    /// Print only the missing crates matching a filter expression over their JSON fields,
+    /// e.g. 'status == "new" && license != "MIT"' (all formats; archives stay complete)
+    #[arg(long, value_name = "EXPR", value_parser = query::Query::parse)]
+    query: Option<query::Query>,
+
+    /// Treat warnings (crates fixable by moving the lockfile back) as blocking
+    #[arg(long)]
+    warnings_as_errors: bool,
+
+    /// Finish with a single stable summary line for log scrapers, e.g.
+    /// "RESULT missing=12 new=3 approved=9 status=fail" (on stderr for non-human formats)
+    #[arg(long)]
+    status_line: bool,
+
+    /// Layout version of JSON reports (also used for plugin input)
+    #[arg(long, default_value_t = SCHEMA_VERSION, value_parser = clap::value_parser!(u32).range(1..=SCHEMA_VERSION as i64))]
+    schema_version: u32,
 }
 
-/// Parse version requirements from a Cargo.toml file
-/// Returns a map of crate names to their version requirements
-fn parse_cargo_toml_requirements(manifest_path: &PathBuf) -> Result<HashMap<String, VersionReq>> {
-    let content = fs::read_to_string(manifest_path).context("Could not read Cargo.toml")?;
-
-    let toml_value: Value = content
-        .parse()
-        .context("Could not parse Cargo.toml as TOML")?;
-
-    let mut requirements: HashMap<String, VersionReq> = HashMap::new();
-
-    // Check all dependency sections
-    let dep_sections = ["dependencies", "dev-dependencies", "build-dependencies"];
-
-    for section in dep_sections {
-        if let Some(deps) = toml_value.get(section).and_then(|v| v.as_table()) {
-            for (name, value) in deps {
-                let version_str = match value {
-                    Value::String(s) => s.clone(),
-                    Value::Table(t) => {
-                        if let Some(Value::String(v)) = t.get("version") {
-                            v.clone()
-                        } else {
-                            continue; // Skip deps without version (git, path, etc.)
-                        }
-                    }
-                    _ => continue,
-                };
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable console report
+    Human,
+    /// JSON report (see the `schema` command)
+    Json,
+    /// One CSV row per missing crate, for spreadsheets
+    Csv,
+    /// YAML report with the same layout as the JSON report
+    Yaml,
+}
 
-                if let Ok(req) = VersionReq::parse(&version_str) {
-                    requirements.insert(name.clone(), req);
-                }
-            }
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Commands about registry_checker itself
+    #[command(name = "self", subcommand)]
+    SelfCommand(SelfCommand),
+
+    /// Three-way merge of registry files, usable as a git merge driver (%O %A %B)
+    /// The merged result is written to <OURS>
+    MergeFile {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+    },
+
+    /// Create an initial registry listing from every crate the matching lockfiles resolve
+    Seed {
+        /// Glob of lockfiles to read, e.g. "repos/**/Cargo.lock" (repeatable)
+        #[arg(long = "lockfiles", value_name = "GLOB", required = true)]
+        lockfiles: Vec<String>,
+
+        /// Registry file to create (text, or YAML with checksums when named *.yaml / *.yml)
+        #[arg(long, value_name = "PATH")]
+        out: PathBuf,
+
+        /// Stop at the first unreadable lockfile instead of skipping it
+        #[arg(long)]
+        fail_fast: bool,
+    },
+
+    /// Fail when the lockfiles require a crate in several semver-incompatible versions
+    /// (rand 0.7 and 0.8), suggesting which dependents to update to converge
+    Majors {
+        /// Glob of lockfiles to read, e.g. "repos/**/Cargo.lock" (repeatable)
+        #[arg(long = "lockfiles", value_name = "GLOB", required = true)]
+        lockfiles: Vec<String>,
+
+        /// Crate allowed in several incompatible versions (wildcards allowed; repeatable)
+        #[arg(long = "allow", value_name = "CRATE")]
+        allow: Vec<String>,
+
+        /// Print CSV instead of text
+        #[arg(long)]
+        csv: bool,
+
+        /// Stop at the first unreadable lockfile instead of skipping it
+        #[arg(long)]
+        fail_fast: bool,
+    },
+
+    /// Chart missing crates and registry size over time from archived reports
+    Trend {
+        /// Directory the reports were archived in with --report-dir
+        #[arg(long, value_name = "PATH")]
+        report_dir: PathBuf,
+
+        /// Print CSV instead of text sparklines
+        #[arg(long)]
+        csv: bool,
+    },
+
+    /// Report which features the scanned projects enable on each registry crate
+    Features {
+        /// Registry file whose crates to report on (text or YAML)
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: PathBuf,
+
+        /// Glob of manifests to scan, e.g. "repos/**/Cargo.toml" (repeatable)
+        #[arg(long = "manifests", value_name = "GLOB", required = true)]
+        manifests: Vec<String>,
+
+        /// Print CSV instead of text
+        #[arg(long)]
+        csv: bool,
+
+        /// Stop at the first project that fails to resolve instead of skipping it
+        #[arg(long)]
+        fail_fast: bool,
+    },
+
+    /// Show how far each registry crate is behind a local crates.io index snapshot
+    Outdated {
+        /// Registry file to compare (text or YAML)
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: PathBuf,
+
+        /// Checkout of a crates.io-style registry index
+        #[arg(long, value_name = "PATH")]
+        index: PathBuf,
+
+        /// Print CSV instead of text
+        #[arg(long)]
+        csv: bool,
+    },
+
+    /// List every crate a crate version needs, from an index snapshot, and check
+    /// them against the registry (to pre-approve a crate before any project uses it)
+    Closure {
+        /// Crate name
+        #[arg(value_name = "CRATE")]
+        name: String,
+
+        /// Exact version of the crate
+        #[arg(value_name = "VERSION")]
+        version: Version,
+
+        /// Registry file to check the closure against (text or YAML)
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: PathBuf,
+
+        /// Checkout of a crates.io-style registry index
+        #[arg(long, value_name = "PATH")]
+        index: PathBuf,
+
+        /// Features to enable on the crate (comma-separated or repeatable)
+        #[arg(long, value_name = "FEATURES", value_delimiter = ',')]
+        features: Vec<String>,
+
+        /// Do not enable the crate's default features
+        #[arg(long)]
+        no_default_features: bool,
+    },
+
+    /// Propose the most valuable crate upgrades for the next registry transfer
+    PlanRefresh {
+        /// Registry file to plan for (text or YAML)
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: PathBuf,
+
+        /// Checkout of a crates.io-style registry index
+        #[arg(long, value_name = "PATH")]
+        index: PathBuf,
+
+        /// Maximum number of upgrades to propose
+        #[arg(long, value_name = "N")]
+        budget: usize,
+
+        /// Archived reports (from --report-dir) whose latest run per project counts as demand
+        #[arg(long, value_name = "PATH")]
+        report_dir: Option<PathBuf>,
+
+        /// Print CSV instead of text
+        #[arg(long)]
+        csv: bool,
+    },
+
+    /// Copy registry entries from one environment to the next, with audit log entries
+    Promote {
+        /// Environment to copy from
+        #[arg(long, value_name = "ENV")]
+        from: String,
+
+        /// Environment to copy to (default: promote_to of the source environment)
+        #[arg(long, value_name = "ENV")]
+        to: Option<String>,
+
+        /// Crate files (serde-1.0.228.crate) or crate names (all versions) to promote
+        #[arg(value_name = "CRATE", required_unless_present = "all")]
+        crates: Vec<String>,
+
+        /// Promote every entry of the source environment
+        #[arg(long, conflicts_with = "crates")]
+        all: bool,
+    },
+
+    /// Merge reviewed entries of a --write-pending listing into the registry
+    ApplyPending {
+        /// Pending listing written by --write-pending
+        #[arg(long, value_name = "PATH")]
+        pending: PathBuf,
+
+        /// Registry file to add the approved entries to (text or YAML)
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: PathBuf,
+
+        /// Crate files (serde-1.0.228.crate) or crate names (all versions) approved
+        #[arg(value_name = "CRATE", required_unless_present = "all")]
+        crates: Vec<String>,
+
+        /// Approve every pending entry
+        #[arg(long, conflicts_with = "crates")]
+        all: bool,
+    },
+
+    /// Re-download registry crates and byte-compare them with the offline store
+    /// to detect tampering (needs network access)
+    Verify {
+        /// Registry file listing the crates to verify (text or YAML)
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: PathBuf,
+
+        /// Directory holding the registry's .crate files
+        #[arg(long, value_name = "PATH")]
+        crate_dir: PathBuf,
+
+        /// Download URL template; {name} and {version} are substituted
+        #[arg(long, value_name = "URL", default_value = verify::CRATES_IO_DOWNLOAD)]
+        download_url: String,
+
+        /// Only verify these crates (all versions; default: every entry)
+        #[arg(value_name = "CRATE")]
+        crates: Vec<String>,
+    },
+
+    /// Show which required crates differ between two targets or two feature sets
+    Compare {
+        /// Path to the Cargo.toml of the project to resolve
+        #[arg(short, long, default_value = "./Cargo.toml")]
+        manifest_path: PathBuf,
+
+        /// Resolve all workspace members instead of the default members
+        #[arg(long)]
+        workspace: bool,
+
+        /// Target triple to resolve for; give it twice to compare two targets
+        #[arg(long = "target", value_name = "TRIPLE")]
+        targets: Vec<String>,
+
+        /// Comma-separated features to enable ("" for the defaults);
+        /// give it twice to compare two feature sets
+        #[arg(long, value_name = "FEATURES")]
+        features: Vec<String>,
+
+        /// Mark crates that are missing from this registry file
+        #[arg(short, long, value_name = "PATH")]
+        registry_file: Option<PathBuf>,
+    },
+
+    /// Print the JSON Schema of the JSON report
+    Schema {
+        /// Layout version to describe
+        #[arg(long, default_value_t = SCHEMA_VERSION, value_parser = clap::value_parser!(u32).range(1..=SCHEMA_VERSION as i64))]
+        schema_version: u32,
+    },
+
+    /// Write man pages for registry_checker and every subcommand
+    GenerateMan {
+        /// Directory to write the pages to, e.g. /usr/local/share/man/man1
+        #[arg(long, value_name = "PATH")]
+        out_dir: PathBuf,
+    },
+}
 
-    Ok(requirements)
+#[derive(Subcommand, Debug)]
+enum SelfCommand {
+    /// Compare the running version against the internal distribution
+    CheckVersion {
+        /// URL returning the latest distributed version (overrides [distribution] url)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Exit with an error when the running version is out of date
+        #[arg(long)]
+        strict: bool,
+    },
 }
 
 /// Parse a crate filename (e.g., "serde-1.0.0.crate") into (name, version)
@@ -83,6 +502,48 @@ fn parse_crate_name_version(crate_file: &str) -> Option<(String, Version)> {
     Some((name.to_string(), version))
 }
 
+/// Remove ANSI escape sequences (colors, hyperlinks) from command output
+/// Handles CSI sequences ("\x1b[...m"), OSC sequences ("\x1b]...\x07") and
+/// plain two-character escapes
+fn strip_ansi_escapes(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            output.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates, terminated by a byte in '@'..='~'
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST ("\x1b\\")
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Any other escape is a single character after ESC
+            _ => {}
+        }
+    }
+
+    output
+}
+
 /// Parse a line from cargo tree output to extract crate name and version
 /// Example: "serde v1.0.228" -> Some(("serde", Version(1.0.228)))
 /// Returns None for dependencies from non-crates.io registries
@@ -119,22 +580,34 @@ fn parse_cargo_tree_line(line: &str) -> Option<(String, Version)> {
     Some((name.to_string(), version))
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-
-    println!("Scanning project dependencies...");
-
+/// Run cargo tree and collect the resolved crates.io dependencies of the project
+fn resolve_dependencies(
+    manifest_path: &PathBuf,
+    selection: &Selection,
+    targets: &[String],
+    features: &str,
+    locked: bool,
+) -> Result<HashMap<String, Version>> {
     // Run cargo tree to get the actual dependency tree
-    let output = Command::new("cargo")
-        .arg("tree")
-        .arg("--manifest-path")
-        .arg(&args.manifest_path)
-        .arg("--edges")
-        .arg("normal") // Only normal dependencies (not dev or build)
-        .arg("--prefix")
-        .arg("none") // Simpler output format
-        .output()
-        .context("Failed to run cargo tree. Is cargo installed?")?;
+    let output = cancel::output(
+        cargo::command("tree")
+            .arg("--manifest-path")
+            .arg(manifest_path)
+            .args(selection.cargo_args())
+            .args(targets.iter().flat_map(|target| ["--target", target]))
+            .args(
+                (!features.is_empty())
+                    .then_some(["--features", features])
+                    .into_iter()
+                    .flatten(),
+            )
+            .arg("--edges")
+            .arg("normal") // Only normal dependencies (not dev or build)
+            .arg("--prefix")
+            .arg("none") // Simpler output format
+            .args(locked.then_some("--locked")), // Never update an existing lockfile
+        "cargo tree",
+    )?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -143,6 +616,8 @@ fn main() -> Result<()> {
 
     let tree_output =
         String::from_utf8(output.stdout).context("cargo tree output was not valid UTF-8")?;
+    // Strip any escapes that still slipped through (e.g. wrapper scripts)
+    let tree_output = strip_ansi_escapes(&tree_output);
 
     // Parse cargo tree output to get all dependencies
     let mut project_deps: HashMap<String, Version> = HashMap::new();
@@ -163,37 +638,52 @@ fn main() -> Result<()> {
         project_deps.remove(&root_name);
     }
 
-    println!("Reading existing registry file: {:?}", args.registry_file);
-    let file_content =
-        fs::read_to_string(&args.registry_file).context("Could not read registry file")?;
-
-    let existing_registry: HashSet<String> = file_content
-        .lines()
-        .map(|line| line.trim().to_string())
-        .filter(|line| !line.is_empty())
-        .collect();
+    Ok(project_deps)
+}
 
-    // Build a map of crate names to their versions in the registry
+/// Build a map of crate names to their versions in the registry
+fn registry_versions(existing_registry: &HashSet<String>) -> HashMap<String, Vec<Version>> {
     let mut registry_versions: HashMap<String, Vec<Version>> = HashMap::new();
-    for crate_file in &existing_registry {
+    for crate_file in existing_registry {
         if let Some((name, version)) = parse_crate_name_version(crate_file) {
             registry_versions.entry(name).or_default().push(version);
         }
     }
+    for versions in registry_versions.values_mut() {
+        versions.sort();
+    }
+    registry_versions
+}
 
-    // Parse Cargo.toml to get version requirements for direct dependencies
-    println!("Parsing Cargo.toml version requirements...");
-    let cargo_requirements = parse_cargo_toml_requirements(&args.manifest_path)?;
+/// Highest registry version older than the resolved one but semver-compatible with it
+/// Cargo could resolve to it instead, e.g. registry 1.0.150 for a lockfile at 1.0.200
+fn compatible_fallback(needed_version: &Version, versions: &[Version]) -> Option<Version> {
+    versions
+        .iter()
+        .filter(|v| *v < needed_version)
+        .filter(|v| {
+            VersionReq::parse(&format!("^{}", v))
+                .map(|req| req.matches(needed_version))
+                .unwrap_or(false)
+        })
+        .max()
+        .cloned()
+}
 
-    // Find missing dependencies: crates from cargo tree where no approved version satisfies the requirement
-    let mut missing_deps: HashMap<String, Version> = HashMap::new();
+/// Find missing dependencies: crates from cargo tree where no approved version satisfies the requirement
+fn find_missing(
+    project_deps: &HashMap<String, Version>,
+    registry_versions: &HashMap<String, Vec<Version>>,
+    cargo_requirements: &HashMap<String, Requirement>,
+) -> Vec<MissingCrate> {
+    let mut missing: Vec<MissingCrate> = Vec::new();
 
-    for (dep_name, needed_version) in &project_deps {
+    for (dep_name, needed_version) in project_deps {
         // First check if there's a version requirement from Cargo.toml (direct dependency)
         // For transitive deps, create a requirement based on the resolved version
-        let version_req = cargo_requirements
-            .get(dep_name)
-            .cloned()
+        let declared = cargo_requirements.get(dep_name);
+        let version_req = declared
+            .map(|requirement| requirement.req.clone())
             .unwrap_or_else(|| {
                 // For transitive deps, create a caret requirement from the resolved version
                 // e.g., if cargo tree shows 1.0.95, create ^1.0.95
@@ -201,119 +691,583 @@ fn main() -> Result<()> {
             });
 
         // Check if any version in the registry satisfies the requirement
-        let has_compatible_version = registry_versions
-            .get(dep_name)
+        let existing_versions = registry_versions.get(dep_name);
+        let has_compatible_version = existing_versions
             .map(|versions| versions.iter().any(|v| version_req.matches(v)))
             .unwrap_or(false);
 
-        if !has_compatible_version {
-            missing_deps.insert(dep_name.clone(), needed_version.clone());
+        if has_compatible_version {
+            continue;
         }
-    }
 
-    if missing_deps.is_empty() {
-        println!("All dependencies from cargo tree are in the offline registry.");
-        return Ok(());
+        let (status, reason) = match existing_versions {
+            // Registry has this crate but no version satisfies the requirement
+            Some(versions) => {
+                let versions_str: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+                (
+                    Status::VersionMismatch,
+                    format!(
+                        "requirement \"{}\" not satisfied by registry versions [{}]",
+                        manifest::canonical(&version_req),
+                        versions_str.join(", ")
+                    ),
+                )
+            }
+            // New dependency - needs approval
+            None => (Status::New, "new dependency".to_string()),
+        };
+
+        // Direct requirements are already checked against the manifest, so only
+        // transitive dependencies can be fixed by moving the lockfile back
+        let fallback = match (declared, existing_versions) {
+            (None, Some(versions)) => compatible_fallback(needed_version, versions),
+            _ => None,
+        };
+
+        missing.push(MissingCrate {
+            name: dep_name.clone(),
+            version: needed_version.clone(),
+            crate_file: format!("{}-{}.crate", dep_name, needed_version),
+            requirement: version_req.to_string(),
+            canonical_requirement: manifest::canonical(&version_req),
+            declared_requirement: declared.map(|requirement| requirement.raw.clone()),
+            status,
+            registry_versions: existing_versions.cloned().unwrap_or_default(),
+            reason,
+            owner: None,
+            declared_in: declared.map(|requirement| requirement.source.clone()),
+            severity: if fallback.is_some() {
+                Severity::Warning
+            } else {
+                Severity::Error
+            },
+            compatible_fallback: fallback,
+            introduced_by: Vec::new(),
+            license: None,
+            superseded_by: None,
+            download_url: None,
+            mirror_url: None,
+            suspicious: Vec::new(),
+            expired_ignore: None,
+        });
     }
 
-    // Display what we found with version analysis
-    let mut missing_sorted: Vec<_> = missing_deps.iter().collect();
-    missing_sorted.sort_by_key(|(name, _)| *name);
+    missing.sort_by(|a, b| a.name.cmp(&b.name));
+    missing
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = Config::load(args.config.as_deref())?;
+    cargo::set_offline(args.offline);
+    cancel::set_timeout(args.timeout);
+    let _handler = cancellable(args.command.as_ref()).then(cancel::install_handler);
+
+    let otlp_endpoint = args
+        .otlp_endpoint
+        .clone()
+        .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+        .filter(|endpoint| !endpoint.is_empty());
+    if otlp_endpoint.is_some() {
+        trace::enable();
+    }
 
-    println!(
-        "Found {} dependencies missing from the offline registry:",
-        missing_deps.len()
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    let result = trace::span(
+        "registry_checker",
+        &[("arguments", &arguments)],
+        || match &args.command {
+            Some(Commands::SelfCommand(SelfCommand::CheckVersion { url, strict })) => {
+                self_check::check_version(&config, url.as_deref(), *strict)
+            }
+            Some(Commands::MergeFile { base, ours, theirs }) => {
+                merge::merge_files(base, ours, theirs)
+            }
+            Some(Commands::Seed {
+                lockfiles,
+                out,
+                fail_fast,
+            }) => seed::seed(lockfiles, out, *fail_fast),
+            Some(Commands::Majors {
+                lockfiles,
+                allow,
+                csv,
+                fail_fast,
+            }) => majors::print_multiple_majors(lockfiles, allow, *csv, *fail_fast),
+            Some(Commands::Trend { report_dir, csv }) => trend::print_trend(report_dir, *csv),
+            Some(Commands::Features {
+                registry_file,
+                manifests,
+                csv,
+                fail_fast,
+            }) => features::print_feature_usage(registry_file, manifests, *csv, *fail_fast),
+            Some(Commands::Outdated {
+                registry_file,
+                index,
+                csv,
+            }) => outdated::print_outdated(registry_file, index, *csv),
+            Some(Commands::Closure {
+                name,
+                version,
+                registry_file,
+                index,
+                features,
+                no_default_features,
+            }) => closure::print_closure(
+                registry_file,
+                index,
+                name,
+                version,
+                features,
+                !no_default_features,
+            ),
+            Some(Commands::PlanRefresh {
+                registry_file,
+                index,
+                budget,
+                report_dir,
+                csv,
+            }) => plan::print_plan(registry_file, index, *budget, report_dir.as_deref(), *csv),
+            Some(Commands::Promote {
+                from,
+                to,
+                crates,
+                all,
+            }) => promote::promote(
+                &config,
+                from,
+                to.as_deref(),
+                crates,
+                *all,
+                chrono::Utc::now(),
+            ),
+            Some(Commands::ApplyPending {
+                pending,
+                registry_file,
+                crates,
+                all,
+            }) => pending::apply_pending(pending, registry_file, crates, *all),
+            Some(Commands::Verify {
+                registry_file,
+                crate_dir,
+                download_url,
+                crates,
+            }) => verify::verify(registry_file, crate_dir, download_url, crates, jobs(&args)),
+            Some(Commands::Compare {
+                manifest_path,
+                workspace,
+                targets,
+                features,
+                registry_file,
+            }) => {
+                let selection = Selection {
+                    workspace: *workspace,
+                    exclude: Vec::new(),
+                };
+                let configurations = compare::Configuration::pair(targets, features)?;
+                compare::compare(
+                    manifest_path,
+                    &selection,
+                    &configurations,
+                    registry_file.as_deref(),
+                )
+            }
+            Some(Commands::Schema { schema_version }) => {
+                let schema = report::json_schema(*schema_version)?;
+                println!("{}", serde_json::to_string_pretty(&schema)?);
+                Ok(())
+            }
+            Some(Commands::GenerateMan { out_dir }) => {
+                let pages = man::generate_man(Args::command(), out_dir)?;
+                eprintln!("Wrote {} man pages to {:?}", pages.len(), out_dir);
+                Ok(())
+            }
+            None => {
+                let result = run_check(&args, &config);
+                if args.status_line {
+                    let line = match &result {
+                        Ok(report) => report::status_line(report, args.warnings_as_errors),
+                        Err(_) => report::ERROR_STATUS_LINE.to_string(),
+                    };
+                    // Keep machine-readable reports and listings on stdout parseable
+                    match args.output {
+                        OutputFormat::Human if !listing_to_stdout(&args) => println!("{}", line),
+                        _ => eprintln!("{}", line),
+                    }
+                }
+                let report = result?;
+                if blocks_on_findings(&args) {
+                    report::check_blocking(&report, args.warnings_as_errors)?;
+                }
+                Ok(())
+            }
+        },
     );
+    if let Some(endpoint) = &otlp_endpoint
+        && let Err(err) = trace::export(endpoint)
+    {
+        eprintln!("Warning: {:#}", err);
+    }
 
-    let mut needs_approval: Vec<(String, String)> = Vec::new(); // (crate, reason)
+    // Resolving rarely creates it, but never leave it behind
+    let _ = std::fs::remove_dir_all(cargo::target_dir());
+    result
+}
 
-    for (dep_name, needed_version) in &missing_sorted {
-        let crate_file = format!("{}-{}.crate", dep_name, needed_version);
+/// Record typosquatting signals on the new crates
+///
+/// Names are compared with the registry's crates and the `--popular-crates` list;
+/// publication data comes from the crates.io API unless running `--offline`.
+fn flag_typosquats(args: &Args, registry: &Registry, missing: &mut [MissingCrate]) -> Result<()> {
+    let mut known: BTreeSet<String> = registry
+        .entries
+        .iter()
+        .filter_map(|entry| parse_crate_name_version(entry).map(|(name, _)| name))
+        .collect();
+    if let Some(path) = &args.popular_crates {
+        known.extend(typosquat::load_popular(path)?);
+    }
 
-        // Get the version requirement for display
-        let version_req = cargo_requirements
-            .get(*dep_name)
-            .map(|r| r.to_string())
-            .unwrap_or_else(|| format!("^{}", needed_version));
+    let new: Vec<&mut MissingCrate> = missing
+        .iter_mut()
+        .filter(|m| m.status == Status::New)
+        .collect();
+    let published = if args.offline {
+        eprintln!("Skipping crates.io lookups for --typosquat (--offline); checking names only");
+        vec![None; new.len()]
+    } else {
+        eprintln!("Looking up {} new crate(s) on crates.io...", new.len());
+        let names: Vec<&str> = new.iter().map(|m| m.name.as_str()).collect();
+        parallel::map(&names, jobs(args), |name| {
+            typosquat::fetch_published(name)
+                .inspect_err(|err| eprintln!("Warning: {:#}", err))
+                .ok()
+        })
+    };
+    cancel::check("crates.io lookups")?;
+
+    let now = chrono::Utc::now();
+    for (missing, published) in new.into_iter().zip(published) {
+        missing.suspicious = typosquat::signals(&missing.name, &known, published.as_ref(), now);
+    }
+    Ok(())
+}
 
-        if let Some(existing_versions) = registry_versions.get(*dep_name) {
-            // Registry has this crate but no version satisfies the requirement
-            let versions_str: Vec<String> =
-                existing_versions.iter().map(|v| v.to_string()).collect();
-            println!(
-                "  {} [requirement: \"{}\", registry has: {}; no compatible version]",
-                crate_file,
-                version_req,
-                versions_str.join(", ")
-            );
-            needs_approval.push((
-                crate_file.clone(),
-                format!(
-                    "requirement \"{}\" not satisfied by registry versions [{}]",
-                    version_req,
-                    versions_str.join(", ")
-                ),
+/// Whether a command cleans up after Ctrl+C: those that run cargo or go over the network and
+/// check for cancellation between steps. The others stop right away on Ctrl+C.
+fn cancellable(command: Option<&Commands>) -> bool {
+    matches!(
+        command,
+        None | Some(
+            Commands::SelfCommand(_)
+                | Commands::Features { .. }
+                | Commands::Verify { .. }
+                | Commands::Compare { .. }
+        )
+    )
+}
+
+/// Threads for per-crate steps, from `--jobs`
+fn jobs(args: &Args) -> usize {
+    args.jobs
+        .map_or_else(parallel::default_jobs, |jobs| jobs as usize)
+}
+
+/// Whether the updated listing goes to stdout (`--registry-file - --write`), leaving the
+/// report to stderr
+fn listing_to_stdout(args: &Args) -> bool {
+    args.write
+        && args
+            .registry_file
+            .as_deref()
+            .is_some_and(registry::is_stdio)
+}
+
+/// Whether blocking findings fail the run: always, except with `--write`, which resolves them
+/// by adding the crates to the registry
+///
+/// `--write-pending` only stages the crates for review; they stay unapproved and missing from
+/// the registry until `apply-pending`, so the run keeps failing on them.
+fn blocks_on_findings(args: &Args) -> bool {
+    !args.write
+}
+
+/// Check the project against the offline registry (the default command)
+fn run_check(args: &Args, config: &Config) -> Result<Report> {
+    let registry_file = match (&args.registry_file, &args.env) {
+        (Some(registry_file), _) => registry_file,
+        (None, Some(env)) => &config.environment(env)?.registry_file,
+        (None, None) => anyhow::bail!("--registry-file or --env is required"),
+    };
+
+    let selection = Selection {
+        workspace: args.workspace,
+        exclude: args.exclude.clone(),
+    };
+    let workspace = Workspace::load(&args.manifest_path)?;
+    let members = workspace.selected_members(&args.manifest_path, &selection);
+
+    eprintln!("Checking Cargo.lock is up to date...");
+    let lockfile = lockfile::lockfile_path(&workspace.root_manifest);
+    lockfile::check_drift(&args.manifest_path, &lockfile)?;
+    // Taken before cargo can generate a lockfile, so the inputs are recorded as given
+    let fingerprint = Fingerprint::new(
+        registry_file,
+        lockfile.exists().then_some(lockfile.as_path()),
+        std::env::args().skip(1).collect(),
+        chrono::Utc::now(),
+    )?;
+    let _generated = lockfile::GeneratedGuard::new(&lockfile);
+
+    eprintln!("Scanning project dependencies...");
+    let mut project_deps = resolve_dependencies(
+        &args.manifest_path,
+        &selection,
+        &args.targets,
+        "",
+        lockfile.exists(),
+    )?;
+    // Workspace members and path dependencies are built from source, never from the registry
+    for member in &workspace.members {
+        project_deps.remove(&member.name);
+    }
+    let targets = Targets::load(&args.targets)?;
+    let graph = DependencyGraph::load(&args.manifest_path, &targets, lockfile.exists())?;
+    for package in graph.path_packages() {
+        if project_deps.get(&package.name) == Some(&package.version) {
+            project_deps.remove(&package.name);
+        }
+    }
+
+    // Toolchain-provided crates are reported separately instead of being checked
+    let provided = Provided::new(config.provided.iter().chain(&args.provided).cloned());
+    let mut provided_deps: Vec<ProvidedCrate> = project_deps
+        .iter()
+        .filter(|(name, _)| provided.contains(name))
+        .map(|(name, version)| ProvidedCrate {
+            name: name.clone(),
+            version: version.clone(),
+            severity: Severity::Info,
+        })
+        .collect();
+    provided_deps.sort_by(|a, b| a.name.cmp(&b.name));
+    project_deps.retain(|name, _| !provided.contains(name));
+
+    eprintln!("Reading existing registry file: {:?}", registry_file);
+    let registry = Registry::load(registry_file)?;
+    cancel::check("registry check")?;
+
+    let registry_versions = registry_versions(&registry.entries);
+
+    // Parse the selected members' Cargo.toml files to get version requirements for direct dependencies
+    eprintln!("Parsing Cargo.toml version requirements...");
+    let workspace_requirements =
+        manifest::parse_workspace_requirements(&workspace::display_path(&workspace.root_manifest))?;
+    let mut cargo_requirements: HashMap<String, Requirement> = HashMap::new();
+    for member in &members {
+        cancel::check("registry check")?;
+        let manifest_path = workspace::display_path(&member.manifest_path);
+        for (name, requirement) in manifest::parse_cargo_toml_requirements(
+            &manifest_path,
+            &workspace_requirements,
+            &targets,
+        )? {
+            // The first member declaring a crate wins
+            cargo_requirements.entry(name).or_insert(requirement);
+        }
+    }
+
+    let mut missing = trace::span("find missing", &[], || {
+        Ok(find_missing(
+            &project_deps,
+            &registry_versions,
+            &cargo_requirements,
+        ))
+    })?;
+
+    // Attribute missing crates to the direct dependencies that pull them in
+    let member_names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+    let enrichment = parallel::map(&missing, jobs(args), |missing| {
+        (
+            graph.introduced_by(&member_names, &missing.name, &missing.version),
+            graph.license(&missing.name, &missing.version),
+            registry.superseded_by(&missing.name).map(str::to_string),
+        )
+    });
+    for (missing, (introduced_by, license, superseded_by)) in missing.iter_mut().zip(enrichment) {
+        missing.introduced_by = introduced_by;
+        missing.license = license;
+        missing.superseded_by = superseded_by;
+    }
+    cancel::check("registry check")?;
+
+    if let Some(owners_file) = &args.owners {
+        let owners = Owners::load(owners_file)?;
+        for missing in &mut missing {
+            let owner = owners.owner_of(&missing.name).unwrap_or(owners::UNOWNED);
+            missing.owner = Some(owner.to_string());
+        }
+    }
+
+    if args.show_urls {
+        for missing in &mut missing {
+            let version = missing.version.to_string();
+            missing.download_url = Some(verify::download_url(
+                verify::CRATES_IO_DOWNLOAD,
+                &missing.name,
+                &version,
             ));
-        } else {
-            // New dependency - needs approval
-            println!(
-                "  {} [WARNING: NEW dependency, requires approval]",
-                crate_file
-            );
-            needs_approval.push((crate_file.clone(), "new dependency".to_string()));
+            missing.mirror_url = config
+                .mirror_url
+                .as_deref()
+                .map(|template| verify::download_url(template, &missing.name, &version));
         }
     }
 
-    // Summary of what needs approval
-    if !needs_approval.is_empty() {
-        println!("\n {} crate(s) require approval:", needs_approval.len());
-        println!("   (no compatible version found in registry)");
+    if args.typosquat {
+        flag_typosquats(args, &registry, &mut missing)?;
     }
 
-    // Detailed list of crates requiring approval
-    if !needs_approval.is_empty() {
-        println!("\n========================================");
-        println!("CRATES REQUIRING APPROVAL:");
-        println!("========================================");
-        for (crate_name, reason) in &needs_approval {
-            println!("  - {} ({})", crate_name, reason);
+    // Temporary exceptions from the config; expired ones resurface as findings
+    let (missing, ignored) =
+        ignore::apply(missing, &config.ignore, chrono::Local::now().date_naive());
+    for expired in missing.iter().filter(|m| m.expired_ignore.is_some()) {
+        eprintln!(
+            "Warning: the ignore entry for {} has expired; it is reported again",
+            expired.crate_file
+        );
+    }
+
+    let mut report = Report::new(
+        args.manifest_path.clone(),
+        registry_file.clone(),
+        project_deps.len(),
+        missing,
+    );
+    report.fingerprint = Some(fingerprint);
+    report.set_provided(provided_deps);
+    report.set_ignored(ignored);
+    report.registry_size = registry.len();
+    report.project = match members.as_slice() {
+        [member] => member.name.clone(),
+        _ => workspace
+            .root_manifest
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+
+    let shown = match &args.query {
+        Some(query) => report.filtered(|missing| {
+            serde_json::to_value(missing).is_ok_and(|value| query.matches(&value))
+        }),
+        None => report.clone(),
+    };
+    let mut out: Box<dyn Write> = if listing_to_stdout(args) {
+        Box::new(io::stderr().lock())
+    } else {
+        Box::new(io::stdout().lock())
+    };
+    match args.output {
+        OutputFormat::Human => {
+            let view = report::View {
+                limit: args.limit,
+                only: args.only,
+            };
+            let layout = layout::Layout::new(
+                args.width.map(usize::from),
+                args.no_wrap,
+                args.compact,
+                args.lang,
+            );
+            report::render_human(&shown, &view, layout, &mut out)?
+        }
+        OutputFormat::Json => {
+            let json = report::to_json(&shown, args.schema_version)?;
+            writeln!(out, "{}", serde_json::to_string_pretty(&json)?)?;
+        }
+        OutputFormat::Csv => report::render_csv(&shown, &mut out)?,
+        OutputFormat::Yaml => {
+            let json = report::to_json(&shown, args.schema_version)?;
+            write!(out, "{}", serde_yaml::to_string(&json)?)?;
         }
-        println!("========================================");
     }
+    out.flush()?;
+    drop(out);
 
-    if args.write {
-        println!("\nMerging and sorting registry file...");
+    if let Some(graph_file) = &args.graph {
+        graph::write_graph(graph_file, &graph::build(&graph, &member_names, &report))?;
+        eprintln!("Dependency graph written to {:?}", graph_file);
+    }
 
-        // Convert missing deps to crate file format
-        let missing_crate_files: HashSet<String> = missing_deps
-            .iter()
-            .map(|(name, version)| format!("{}-{}.crate", name, version))
-            .collect();
+    if let Some(report_dir) = &args.report_dir {
+        let run_dir = archive::write_report(report_dir, &report, chrono::Utc::now())?;
+        eprintln!("Report archived in {:?}", run_dir);
+    }
 
-        // 1. Combine existing and missing
-        let mut full_list: Vec<String> = existing_registry
-            .union(&missing_crate_files)
-            .cloned()
-            .collect();
+    plugin::run_plugins(&args.plugins, &report, args.schema_version)?;
 
-        // 2. Sort the full list
-        full_list.sort();
+    if args.create_ticket {
+        let ticket = config
+            .ticket
+            .as_ref()
+            .context("--create-ticket requires a [ticket] section in the config file")?;
+        ticket::create_ticket(ticket, &report)?;
+    }
 
-        // 3. Overwrite the file with the sorted content
-        let file = File::create(&args.registry_file)
-            .context("Failed to open registry file for writing")?;
-        let mut writer = BufWriter::new(file);
+    if report.missing.is_empty() {
+        // A pipeline still expects the listing, even with nothing to add
+        if listing_to_stdout(args) {
+            registry.save(registry_file)?;
+        }
+        return Ok(report);
+    }
 
-        for line in full_list {
-            writeln!(writer, "{}", line)?;
+    if !args.write && args.write_pending.is_none() {
+        eprintln!("\n(Run with --write to add these and sort the file)");
+        return Ok(report);
+    }
+
+    // Superseded crates are migrated away from, never approved
+    let mut additions = Vec::new();
+    for missing in &report.missing {
+        match &missing.superseded_by {
+            Some(replacement) => eprintln!(
+                "Not adding {}: superseded by {}",
+                missing.crate_file, replacement
+            ),
+            None => additions.push(missing.crate_file.clone()),
         }
+    }
+
+    if let Some(pending_file) = &args.write_pending {
+        let staged = pending::stage(pending_file, &additions)?;
+        eprintln!(
+            "\nAdded {} entries to {:?} for review (run apply-pending once approved)",
+            staged.len(),
+            pending_file
+        );
+        return Ok(report);
+    }
+
+    eprintln!("\nMerging and sorting registry file...");
 
-        println!("Successfully updated and sorted {:?}", args.registry_file);
+    // Combine existing and missing, then overwrite the file with the sorted content
+    let mut registry = registry;
+    registry.entries.extend(additions.iter().cloned());
+    registry.save(registry_file)?;
+    if let Some(env) = &args.env {
+        promote::record_additions(config, env, &additions, chrono::Utc::now())?;
+    }
+
+    if registry::is_stdio(registry_file) {
+        eprintln!("Printed the updated and sorted listing to stdout");
     } else {
-        println!("\n(Run with --write to add these and sort the file)");
+        eprintln!("Successfully updated and sorted {:?}", registry_file);
     }
 
-    Ok(())
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -376,6 +1330,58 @@ mod tests {
         assert!(result.is_some());
     }
 
+    #[test]
+    fn test_strip_ansi_escapes() {
+        // Plain text is untouched
+        assert_eq!(strip_ansi_escapes("serde v1.0.228"), "serde v1.0.228");
+
+        // Color codes around name and version
+        assert_eq!(
+            strip_ansi_escapes("\x1b[1m\x1b[32mserde\x1b[0m v1.0.228\x1b[0m"),
+            "serde v1.0.228"
+        );
+
+        // OSC 8 hyperlink terminated by ST and BEL
+        assert_eq!(
+            strip_ansi_escapes("\x1b]8;;https://crates.io\x1b\\anyhow\x1b]8;;\x07 v1.0.100"),
+            "anyhow v1.0.100"
+        );
+
+        // Stripped lines parse like uncolored ones
+        let line = strip_ansi_escapes("\x1b[1;33mclap_derive\x1b[0m v4.5.49 (proc-macro)");
+        let (name, version) = parse_cargo_tree_line(&line).unwrap();
+        assert_eq!(name, "clap_derive");
+        assert_eq!(version, Version::parse("4.5.49").unwrap());
+    }
+
+    #[test]
+    fn test_compatible_fallback() {
+        let versions: Vec<Version> = ["0.9.0", "1.0.100", "1.0.150", "1.2.0", "2.0.0"]
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect();
+
+        // Highest older compatible version
+        assert_eq!(
+            compatible_fallback(&Version::parse("1.0.200").unwrap(), &versions),
+            Some(Version::parse("1.0.150").unwrap())
+        );
+        assert_eq!(
+            compatible_fallback(&Version::parse("1.3.0").unwrap(), &versions),
+            Some(Version::parse("1.2.0").unwrap())
+        );
+
+        // Nothing compatible below a new major, or below 0.x minors
+        assert_eq!(
+            compatible_fallback(&Version::parse("3.0.0").unwrap(), &versions),
+            None
+        );
+        assert_eq!(
+            compatible_fallback(&Version::parse("0.10.0").unwrap(), &versions),
+            None
+        );
+    }
+
     #[test]
     fn test_version_req_matching() {
         // Test that ^1.0 matches 1.8.0
@@ -486,19 +1492,30 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_cargo_toml_requirements() {
-        let requirements = parse_cargo_toml_requirements(&PathBuf::from("Cargo.toml"))
-            .expect("Should parse Cargo.toml");
-
-        // Check direct dependencies from this project's Cargo.toml
-        assert!(requirements.contains_key("clap"));
-        assert!(requirements.contains_key("anyhow"));
-        assert!(requirements.contains_key("semver"));
-        assert!(requirements.contains_key("toml"));
-
-        // Verify the requirements match what's in Cargo.toml
-        assert_eq!(requirements["anyhow"].to_string(), "^1.0");
-        assert_eq!(requirements["semver"].to_string(), "^1.0");
-        assert_eq!(requirements["toml"].to_string(), "^0.8");
+    fn test_listing_to_stdout() {
+        let args =
+            |extra: &[&str]| Args::parse_from(["registry_checker", "-r"].iter().chain(extra));
+        assert!(listing_to_stdout(&args(&["-", "--write"])));
+        // Reading stdin alone leaves stdout to the report
+        assert!(!listing_to_stdout(&args(&["-"])));
+        assert!(!listing_to_stdout(&args(&["registry.txt", "--write"])));
+    }
+
+    #[test]
+    fn test_blocks_on_findings() {
+        let args = |extra: &[&str]| {
+            Args::parse_from(
+                ["registry_checker", "-r", "registry.txt"]
+                    .iter()
+                    .chain(extra),
+            )
+        };
+        assert!(blocks_on_findings(&args(&[])));
+        assert!(!blocks_on_findings(&args(&["--write"])));
+        // Staged crates are still missing from the registry
+        assert!(blocks_on_findings(&args(&[
+            "--write-pending",
+            "pending.txt"
+        ])));
     }
 }
@@ -0,0 +1,309 @@
+use crate::fleet::ProjectErrors;
+use crate::lockfile::{self, LockedPackage};
+use crate::owners::glob_match;
+use crate::report::csv_field;
+use crate::seed;
+use crate::trace;
+use anyhow::Result;
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Semver-compatibility line of a version: versions on different lines cannot be unified
+/// by cargo (1.2.3 and 1.9.0 share line 1; 0.7.3 and 0.8.0 do not)
+type Line = (u64, u64, u64);
+
+fn compat_line(version: &Version) -> Line {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+/// "1", "0.7" or "0.0.3"
+fn line_label(line: Line) -> String {
+    match line {
+        (0, 0, patch) => format!("0.0.{}", patch),
+        (0, minor, _) => format!("0.{}", minor),
+        (major, _, _) => major.to_string(),
+    }
+}
+
+/// Where the scanned projects use one compatibility line of a crate
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineUsage {
+    pub versions: BTreeSet<Version>,
+    /// Lockfiles resolving a version of this line
+    pub projects: BTreeSet<PathBuf>,
+    /// Packages depending on this line, as "name version"
+    pub needed_by: BTreeSet<String>,
+}
+
+/// A crate required in several semver-incompatible versions
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipleMajors {
+    pub name: String,
+    /// Usage per compatibility line, oldest first
+    pub lines: BTreeMap<Line, LineUsage>,
+}
+
+/// Whether a lockfile dependency entry ("name", "name version" or "name version (source)")
+/// refers to the package
+fn refers_to(entry: &str, package: &LockedPackage) -> bool {
+    let mut parts = entry.split_whitespace();
+    parts.next() == Some(package.name.as_str())
+        && parts
+            .next()
+            .is_none_or(|version| version == package.version.to_string())
+}
+
+#[derive(Debug, Default)]
+struct Usage(BTreeMap<String, BTreeMap<Line, LineUsage>>);
+
+impl Usage {
+    fn add_lockfile(&mut self, path: &Path, packages: &[LockedPackage]) {
+        for package in packages.iter().filter(|p| p.is_from_registry()) {
+            let usage = self
+                .0
+                .entry(package.name.clone())
+                .or_default()
+                .entry(compat_line(&package.version))
+                .or_default();
+            usage.versions.insert(package.version.clone());
+            usage.projects.insert(path.to_path_buf());
+            for dependent in packages {
+                if dependent
+                    .dependencies
+                    .iter()
+                    .any(|entry| refers_to(entry, package))
+                {
+                    usage
+                        .needed_by
+                        .insert(format!("{} {}", dependent.name, dependent.version));
+                }
+            }
+        }
+    }
+
+    /// Crates used on more than one line, except the allowed ones
+    fn finish(self, allow: &[String]) -> Vec<MultipleMajors> {
+        self.0
+            .into_iter()
+            .filter(|(name, lines)| {
+                lines.len() > 1 && !allow.iter().any(|pattern| glob_match(pattern, name))
+            })
+            .map(|(name, lines)| MultipleMajors { name, lines })
+            .collect()
+    }
+}
+
+fn join<T: ToString>(items: impl IntoIterator<Item = T>, separator: &str) -> String {
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn render_text(found: &[MultipleMajors], out: &mut impl Write) -> io::Result<()> {
+    for crate_majors in found {
+        writeln!(
+            out,
+            "{}: {} incompatible versions in use",
+            crate_majors.name,
+            crate_majors.lines.len()
+        )?;
+        for (line, usage) in &crate_majors.lines {
+            write!(
+                out,
+                "  {} ({}): {} project(s)",
+                line_label(*line),
+                join(&usage.versions, ", "),
+                usage.projects.len()
+            )?;
+            if !usage.needed_by.is_empty() {
+                write!(out, ", needed by {}", join(&usage.needed_by, ", "))?;
+            }
+            writeln!(out)?;
+        }
+
+        // Older lines only survive because something still depends on them
+        let (newest, _) = crate_majors.lines.last_key_value().expect("two lines");
+        let older = crate_majors.lines.values().rev().skip(1);
+        let mut update = BTreeSet::new();
+        let mut projects = BTreeSet::new();
+        for usage in older {
+            update.extend(usage.needed_by.iter());
+            projects.extend(usage.projects.iter().map(|path| path.display().to_string()));
+        }
+        write!(
+            out,
+            "  suggestion: converge on {} {}",
+            crate_majors.name,
+            line_label(*newest)
+        )?;
+        if !update.is_empty() {
+            write!(out, " by updating {}", join(update, ", "))?;
+        }
+        writeln!(out, " (in {})", join(projects, ", "))?;
+    }
+    Ok(())
+}
+
+fn render_csv(found: &[MultipleMajors], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "crate,line,versions,projects,needed_by")?;
+    for crate_majors in found {
+        for (line, usage) in &crate_majors.lines {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&crate_majors.name),
+                line_label(*line),
+                join(&usage.versions, " "),
+                usage.projects.len(),
+                csv_field(&join(
+                    usage.needed_by.iter().map(|dep| dep.replace(' ', "@")),
+                    " "
+                ))
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Report crates the matching lockfiles require in several semver-incompatible versions,
+/// failing when there are any that `allow` does not accept
+///
+/// Unreadable lockfiles are skipped and listed at the end unless `fail_fast` is set.
+pub fn print_multiple_majors(
+    patterns: &[String],
+    allow: &[String],
+    csv: bool,
+    fail_fast: bool,
+) -> Result<()> {
+    let mut lockfiles = Vec::new();
+    for pattern in patterns {
+        lockfiles.extend(seed::glob_files(pattern)?);
+    }
+    lockfiles.sort();
+    lockfiles.dedup();
+    if lockfiles.is_empty() {
+        anyhow::bail!("No lockfiles match {}", patterns.join(", "));
+    }
+
+    let mut errors = ProjectErrors::new(fail_fast);
+    let mut usage = Usage::default();
+    for path in &lockfiles {
+        let packages = trace::span("lockfile parse", &[("path", &path.display())], || {
+            lockfile::packages(path)
+        });
+        if let Some(packages) = errors.check(path, packages)? {
+            usage.add_lockfile(path, &packages);
+        }
+    }
+    let found = usage.finish(allow);
+    eprintln!(
+        "{} crate(s) required in several incompatible versions across {} lockfile(s)",
+        found.len(),
+        lockfiles.len()
+    );
+
+    let mut out = io::stdout().lock();
+    if csv {
+        render_csv(&found, &mut out)?;
+        // Keep the CSV parseable
+        errors.render(&mut io::stderr())?;
+    } else {
+        render_text(&found, &mut out)?;
+        errors.render(&mut out)?;
+    }
+    if !found.is_empty() {
+        anyhow::bail!(
+            "{} crate(s) are required in several incompatible versions (converge them, or accept one with --allow)",
+            found.len()
+        );
+    }
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, dependencies: &[&str]) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: Version::parse(version).unwrap(),
+            source: Some("registry+https://github.com/rust-lang/crates.io-index".to_string()),
+            checksum: None,
+            dependencies: dependencies.iter().map(|dep| dep.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_compat_line() {
+        let line = |version: &str| line_label(compat_line(&Version::parse(version).unwrap()));
+        assert_eq!(line("1.2.3"), "1");
+        assert_eq!(line("0.7.3"), "0.7");
+        assert_eq!(line("0.0.3"), "0.0.3");
+        assert_eq!(
+            compat_line(&Version::parse("1.0.0").unwrap()),
+            compat_line(&Version::parse("1.9.2").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_multiple_majors() {
+        let mut app = vec![
+            package("rand", "0.7.3", &[]),
+            package("rand", "0.8.5", &[]),
+            package("legacy", "0.1.0", &["rand 0.7.3"]),
+            package("serde", "1.0.200", &[]),
+            package("syn", "1.0.109", &[]),
+            package("syn", "2.0.1", &[]),
+        ];
+        app.push(LockedPackage {
+            source: None,
+            ..package("app", "0.1.0", &["legacy", "rand 0.8.5", "serde"])
+        });
+        let tool = vec![
+            package("rand", "0.8.4", &[]),
+            package("serde", "1.0.228", &[]),
+            package("tool", "0.2.0", &["rand"]),
+        ];
+
+        let mut usage = Usage::default();
+        usage.add_lockfile(Path::new("app/Cargo.lock"), &app);
+        usage.add_lockfile(Path::new("tool/Cargo.lock"), &tool);
+        let found = usage.finish(&["syn".to_string()]);
+
+        // serde shares one line; syn is allowed
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "rand");
+        let newest = &found[0].lines[&(0, 8, 0)];
+        assert_eq!(newest.projects.len(), 2);
+        assert_eq!(
+            newest.needed_by,
+            ["app 0.1.0", "tool 0.2.0"].map(String::from).into()
+        );
+
+        let mut out = Vec::new();
+        render_text(&found, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "rand: 2 incompatible versions in use\n\
+             \x20 0.7 (0.7.3): 1 project(s), needed by legacy 0.1.0\n\
+             \x20 0.8 (0.8.4, 0.8.5): 2 project(s), needed by app 0.1.0, tool 0.2.0\n\
+             \x20 suggestion: converge on rand 0.8 by updating legacy 0.1.0 (in app/Cargo.lock)\n"
+        );
+
+        let mut out = Vec::new();
+        render_csv(&found, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().nth(1),
+            Some("rand,0.7,0.7.3,1,legacy@0.1.0")
+        );
+    }
+}
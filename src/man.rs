@@ -0,0 +1,56 @@
+use anyhow::{Context, Result};
+use clap::Command;
+use clap_mangen::Man;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Write the page of a built command, then those of its visible subcommands
+fn write_pages(command: &Command, out_dir: &Path, written: &mut Vec<PathBuf>) -> Result<()> {
+    let man = Man::new(command.clone());
+    let path = out_dir.join(man.get_filename());
+    let mut content = Vec::new();
+    man.render(&mut content)?;
+    fs::write(&path, content).with_context(|| format!("Could not write {:?}", path))?;
+    written.push(path);
+
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        write_pages(sub, out_dir, written)?;
+    }
+    Ok(())
+}
+
+/// Write a man page for the tool and every subcommand into `out_dir`
+pub fn generate_man(command: Command, out_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut command = command.disable_help_subcommand(true);
+    // Building fills in the help/version flags and the full names of subcommands
+    command.build();
+    fs::create_dir_all(out_dir).with_context(|| format!("Could not create {:?}", out_dir))?;
+    let mut written = Vec::new();
+    write_pages(&command, out_dir, &mut written)?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_generate_man() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = generate_man(crate::Args::command(), dir.path()).unwrap();
+        let page = |name: &str| {
+            fs::read_to_string(dir.path().join(name)).unwrap_or_else(|_| panic!("no page {}", name))
+        };
+
+        assert!(page("registry_checker.1").contains("registry\\-file"));
+        assert!(page("registry_checker-promote.1").contains("registry_checker\\-promote"));
+        // Nested subcommands get their own page, clap's help command none
+        page("registry_checker-self-check-version.1");
+        assert!(
+            !written
+                .iter()
+                .any(|path| path.to_string_lossy().contains("help"))
+        );
+    }
+}
@@ -0,0 +1,481 @@
+use crate::platform::Targets;
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use semver::{Comparator, Op, Prerelease, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Spanned, Value};
+
+/// Where a version requirement was declared
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Provenance {
+    pub manifest: PathBuf,
+    /// TOML key path of the dependency, e.g. "dependencies.serde"
+    pub key_path: String,
+    /// 1-based line of the dependency key
+    pub line: usize,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} [{}]",
+            self.manifest.display(),
+            self.line,
+            self.key_path
+        )
+    }
+}
+
+/// A version requirement declared in a manifest
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    pub req: VersionReq,
+    /// The requirement exactly as written in the manifest, e.g. "1.0"
+    pub raw: String,
+    pub source: Provenance,
+}
+
+fn version(major: u64, minor: u64, patch: u64, pre: &Prerelease) -> Version {
+    Version {
+        pre: pre.clone(),
+        ..Version::new(major, minor, patch)
+    }
+}
+
+/// Explicit lower and upper bounds of a comparator, as `(">=", version)` / `("<", version)` pairs
+fn bounds(comparator: &Comparator) -> Option<Vec<(&'static str, Version)>> {
+    let Comparator {
+        major,
+        minor,
+        patch,
+        ..
+    } = *comparator;
+    let pre = &comparator.pre;
+    let none = &Prerelease::EMPTY;
+    // First version past the given parts, e.g. 1.2 -> 1.3.0
+    let after = match (minor, patch) {
+        (None, _) => version(major + 1, 0, 0, none),
+        (Some(minor), None) => version(major, minor + 1, 0, none),
+        (Some(minor), Some(patch)) => version(major, minor, patch + 1, none),
+    };
+    let floor = version(major, minor.unwrap_or(0), patch.unwrap_or(0), pre);
+    Some(match comparator.op {
+        Op::Exact if patch.is_some() => vec![("=", floor)],
+        Op::Exact | Op::Wildcard => vec![(">=", floor), ("<", after)],
+        Op::Greater if patch.is_some() => vec![(">", floor)],
+        Op::Greater => vec![(">=", after)],
+        Op::GreaterEq => vec![(">=", floor)],
+        Op::Less => vec![("<", floor)],
+        Op::LessEq if patch.is_some() => vec![("<=", floor)],
+        Op::LessEq => vec![("<", after)],
+        Op::Tilde => {
+            let upper = match minor {
+                None => version(major + 1, 0, 0, none),
+                Some(minor) => version(major, minor + 1, 0, none),
+            };
+            vec![(">=", floor), ("<", upper)]
+        }
+        Op::Caret => {
+            // The upper bound bumps the leftmost non-zero part that was written
+            let upper = match (major, minor, patch) {
+                (0, Some(0), Some(patch)) => version(0, 0, patch + 1, none),
+                (0, Some(minor), _) if minor > 0 || patch.is_none() => {
+                    version(0, minor + 1, 0, none)
+                }
+                _ => version(major + 1, 0, 0, none),
+            };
+            vec![(">=", floor), ("<", upper)]
+        }
+        _ => return None,
+    })
+}
+
+/// Canonical form of a requirement with every bound spelled out, so equivalent
+/// requirements read the same: "1", "1.0", "^1.0" and "1.*" all become ">=1.0.0, <2.0.0"
+///
+/// A range covering a single release is shown as "=x.y.z".
+pub fn canonical(req: &VersionReq) -> String {
+    if req.comparators.is_empty() {
+        return "*".to_string();
+    }
+    let parts: Vec<String> = req
+        .comparators
+        .iter()
+        .flat_map(|comparator| match bounds(comparator) {
+            Some(bounds) => match bounds.as_slice() {
+                [(">=", lower), ("<", upper)]
+                    if lower.pre.is_empty()
+                        && *upper == Version::new(lower.major, lower.minor, lower.patch + 1) =>
+                {
+                    vec![format!("={}", lower)]
+                }
+                _ => bounds
+                    .iter()
+                    .map(|(op, version)| format!("{}{}", op, version))
+                    .collect(),
+            },
+            None => vec![comparator.to_string()],
+        })
+        .collect();
+    parts.join(", ")
+}
+
+/// Dependency table keyed by crate name, keeping the position of each key
+type DependencyTable = BTreeMap<Spanned<String>, Value>;
+
+/// The parts of Cargo.toml we read requirements from
+#[derive(Deserialize)]
+struct ManifestDependencies {
+    workspace: Option<WorkspaceDependencies>,
+    #[serde(default)]
+    dependencies: DependencyTable,
+    #[serde(default, rename = "dev-dependencies")]
+    dev_dependencies: DependencyTable,
+    #[serde(default, rename = "build-dependencies")]
+    build_dependencies: DependencyTable,
+    /// Platform-specific dependencies, keyed by target triple or `cfg(...)` expression
+    #[serde(default)]
+    target: BTreeMap<String, TargetDependencies>,
+}
+
+#[derive(Deserialize)]
+struct TargetDependencies {
+    #[serde(default)]
+    dependencies: DependencyTable,
+    #[serde(default, rename = "dev-dependencies")]
+    dev_dependencies: DependencyTable,
+    #[serde(default, rename = "build-dependencies")]
+    build_dependencies: DependencyTable,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceDependencies {
+    #[serde(default)]
+    dependencies: DependencyTable,
+}
+
+/// 1-based line number of a byte offset
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+/// Version requirement string of a dependency entry
+/// Returns None for deps without version (git, path, workspace inherited, etc.)
+fn version_of(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s),
+        Value::Table(t) => t.get("version").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Whether a dependency entry inherits from `[workspace.dependencies]`
+fn inherits_workspace(value: &Value) -> bool {
+    value
+        .as_table()
+        .and_then(|t| t.get("workspace"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn read_manifest(manifest_path: &Path) -> Result<(String, ManifestDependencies)> {
+    let content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Could not read {}", manifest_path.display()))?;
+    let manifest = toml::from_str(&content)
+        .with_context(|| format!("Could not parse {} as TOML", manifest_path.display()))?;
+    Ok((content, manifest))
+}
+
+/// Add the requirements of one dependency table
+fn collect_requirements(
+    content: &str,
+    manifest_path: &Path,
+    section: &str,
+    deps: &DependencyTable,
+    workspace_requirements: &HashMap<String, Requirement>,
+    requirements: &mut HashMap<String, Requirement>,
+) {
+    for (name, value) in deps {
+        if inherits_workspace(value) {
+            if let Some(requirement) = workspace_requirements.get(name.get_ref()) {
+                requirements.insert(name.get_ref().clone(), requirement.clone());
+            }
+            continue;
+        }
+
+        let Some(version_str) = version_of(value) else {
+            continue;
+        };
+
+        if let Ok(req) = VersionReq::parse(version_str) {
+            let source = Provenance {
+                manifest: manifest_path.to_path_buf(),
+                key_path: format!("{}.{}", section, name.get_ref()),
+                line: line_of(content, name.span().start),
+            };
+            let requirement = Requirement {
+                req,
+                raw: version_str.to_string(),
+                source,
+            };
+            requirements.insert(name.get_ref().clone(), requirement);
+        }
+    }
+}
+
+/// Parse the `[workspace.dependencies]` requirements of a workspace root manifest
+pub fn parse_workspace_requirements(root_manifest: &Path) -> Result<HashMap<String, Requirement>> {
+    let (content, manifest) = read_manifest(root_manifest)?;
+
+    let mut requirements: HashMap<String, Requirement> = HashMap::new();
+    if let Some(workspace) = &manifest.workspace {
+        collect_requirements(
+            &content,
+            root_manifest,
+            "workspace.dependencies",
+            &workspace.dependencies,
+            &HashMap::new(),
+            &mut requirements,
+        );
+    }
+
+    Ok(requirements)
+}
+
+/// Parse version requirements from a Cargo.toml file
+/// Returns a map of crate names to their version requirements and where they were declared.
+/// Dependencies with `workspace = true` take their requirement from `workspace_requirements`.
+/// `[target.<spec>.*]` sections are only included when they apply to one of `targets`.
+pub fn parse_cargo_toml_requirements(
+    manifest_path: &Path,
+    workspace_requirements: &HashMap<String, Requirement>,
+    targets: &Targets,
+) -> Result<HashMap<String, Requirement>> {
+    let (content, manifest) = read_manifest(manifest_path)?;
+
+    let mut requirements: HashMap<String, Requirement> = HashMap::new();
+
+    // Check all dependency sections, including those of platforms we build for
+    let mut dep_sections = vec![
+        ("dependencies".to_string(), &manifest.dependencies),
+        ("dev-dependencies".to_string(), &manifest.dev_dependencies),
+        (
+            "build-dependencies".to_string(),
+            &manifest.build_dependencies,
+        ),
+    ];
+    for (spec, target) in &manifest.target {
+        if !targets.matches(spec) {
+            continue;
+        }
+        dep_sections.push((
+            format!("target.'{}'.dependencies", spec),
+            &target.dependencies,
+        ));
+        dep_sections.push((
+            format!("target.'{}'.dev-dependencies", spec),
+            &target.dev_dependencies,
+        ));
+        dep_sections.push((
+            format!("target.'{}'.build-dependencies", spec),
+            &target.build_dependencies,
+        ));
+    }
+
+    for (section, deps) in &dep_sections {
+        collect_requirements(
+            &content,
+            manifest_path,
+            section,
+            deps,
+            workspace_requirements,
+            &mut requirements,
+        );
+    }
+
+    Ok(requirements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::ALL_TARGETS;
+    use crate::platform::tests::linux;
+
+    fn all_targets() -> Targets {
+        Targets::load(&[ALL_TARGETS.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_parse_cargo_toml_requirements() {
+        let requirements = parse_cargo_toml_requirements(
+            &PathBuf::from("Cargo.toml"),
+            &HashMap::new(),
+            &all_targets(),
+        )
+        .expect("Should parse Cargo.toml");
+
+        // Check direct dependencies from this project's Cargo.toml
+        assert!(requirements.contains_key("clap"));
+        assert!(requirements.contains_key("anyhow"));
+        assert!(requirements.contains_key("semver"));
+        assert!(requirements.contains_key("toml"));
+
+        // Verify the requirements match what's in Cargo.toml
+        assert_eq!(requirements["anyhow"].req.to_string(), "^1.0");
+        assert_eq!(requirements["semver"].req.to_string(), "^1.0");
+        assert_eq!(requirements["toml"].req.to_string(), "^0.8");
+        assert_eq!(requirements["toml"].raw, "0.8");
+    }
+
+    #[test]
+    fn test_canonical() {
+        let canonical = |req: &str| canonical(&VersionReq::parse(req).unwrap());
+
+        for same in ["1", "1.0", "^1.0", "^1", "1.*", "~1", "=1"] {
+            assert_eq!(canonical(same), ">=1.0.0, <2.0.0", "{}", same);
+        }
+        assert_eq!(canonical("1.2.3"), ">=1.2.3, <2.0.0");
+        assert_eq!(canonical("~1.2.3"), ">=1.2.3, <1.3.0");
+        assert_eq!(canonical("0.2"), ">=0.2.0, <0.3.0");
+        assert_eq!(canonical("0.2.3"), ">=0.2.3, <0.3.0");
+        assert_eq!(canonical("0"), ">=0.0.0, <1.0.0");
+        assert_eq!(canonical("0.0"), ">=0.0.0, <0.1.0");
+        assert_eq!(canonical("0.0.3"), "=0.0.3");
+        assert_eq!(canonical("=1.2.3"), "=1.2.3");
+        assert_eq!(canonical("=1.2.3-rc.1"), "=1.2.3-rc.1");
+        assert_eq!(canonical("<=1.9"), "<1.10.0");
+        assert_eq!(canonical(">1.2"), ">=1.3.0");
+        assert_eq!(canonical(">1.2.3"), ">1.2.3");
+        assert_eq!(canonical(">= 1.2, < 1.5"), ">=1.2.0, <1.5.0");
+        assert_eq!(canonical("*"), "*");
+    }
+
+    #[test]
+    fn test_requirement_provenance() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let manifest = dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\n\
+             name = \"api\"\n\
+             \n\
+             [dependencies]\n\
+             anyhow = \"1.0\"\n\
+             local = { path = \"../local\" }\n\
+             \n\
+             [dependencies.serde]\n\
+             version = \"1.0.200\"\n\
+             features = [\"derive\"]\n\
+             \n\
+             [build-dependencies]\n\
+             cc = { version = \"1\" }\n",
+        )
+        .unwrap();
+
+        let requirements =
+            parse_cargo_toml_requirements(&manifest, &HashMap::new(), &all_targets()).unwrap();
+
+        assert!(!requirements.contains_key("local"));
+
+        let anyhow = &requirements["anyhow"].source;
+        assert_eq!(anyhow.key_path, "dependencies.anyhow");
+        assert_eq!(anyhow.line, 5);
+
+        let serde = &requirements["serde"].source;
+        assert_eq!(serde.key_path, "dependencies.serde");
+        assert_eq!(serde.line, 8);
+        assert_eq!(requirements["serde"].req.to_string(), "^1.0.200");
+
+        let cc = &requirements["cc"].source;
+        assert_eq!(cc.key_path, "build-dependencies.cc");
+        assert_eq!(
+            cc.to_string(),
+            format!("{}:13 [build-dependencies.cc]", manifest.display())
+        );
+    }
+
+    #[test]
+    fn test_workspace_inherited_requirements() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("api")).unwrap();
+        let root = dir.join("Cargo.toml");
+        let member = dir.join("api").join("Cargo.toml");
+        fs::write(
+            &root,
+            "[workspace]\n\
+             members = [\"api\"]\n\
+             \n\
+             [workspace.dependencies]\n\
+             serde = { version = \"1.0.200\", features = [\"derive\"] }\n",
+        )
+        .unwrap();
+        fs::write(
+            &member,
+            "[package]\n\
+             name = \"api\"\n\
+             \n\
+             [dependencies]\n\
+             serde = { workspace = true }\n\
+             anyhow = \"1\"\n",
+        )
+        .unwrap();
+
+        let workspace_requirements = parse_workspace_requirements(&root).unwrap();
+        let requirements =
+            parse_cargo_toml_requirements(&member, &workspace_requirements, &all_targets())
+                .unwrap();
+
+        // Inherited requirement points at the workspace root declaration
+        let serde = &requirements["serde"];
+        assert_eq!(serde.req.to_string(), "^1.0.200");
+        assert_eq!(serde.source.manifest, root);
+        assert_eq!(serde.source.key_path, "workspace.dependencies.serde");
+        assert_eq!(serde.source.line, 5);
+
+        assert_eq!(requirements["anyhow"].source.manifest, member);
+    }
+
+    #[test]
+    fn test_target_specific_requirements() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let manifest = dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\n\
+             name = \"app\"\n\
+             \n\
+             [target.'cfg(windows)'.dependencies]\n\
+             winapi = \"0.3\"\n\
+             \n\
+             [target.'cfg(unix)'.dependencies]\n\
+             libc = \"0.2\"\n",
+        )
+        .unwrap();
+
+        let requirements =
+            parse_cargo_toml_requirements(&manifest, &HashMap::new(), &all_targets());
+        let linux_requirements =
+            parse_cargo_toml_requirements(&manifest, &HashMap::new(), &linux());
+
+        let requirements = requirements.unwrap();
+        assert!(requirements.contains_key("winapi"));
+        assert_eq!(
+            requirements["libc"].source.key_path,
+            "target.'cfg(unix)'.dependencies.libc"
+        );
+
+        // Windows-only dependencies don't apply to Linux
+        let linux_requirements = linux_requirements.unwrap();
+        assert!(linux_requirements.contains_key("libc"));
+        assert!(!linux_requirements.contains_key("winapi"));
+    }
+}
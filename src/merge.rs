@@ -0,0 +1,87 @@
+use crate::registry;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// Non-empty, trimmed entries of a registry listing
+fn entries(content: &str) -> BTreeSet<String> {
+    content
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Three-way merge of registry listings
+/// Entries added on either side are kept; entries removed on either side are dropped.
+/// The result is sorted and free of duplicates, so the merge never conflicts.
+fn merge_entries(base: &str, ours: &str, theirs: &str) -> Vec<String> {
+    let base = entries(base);
+    let ours = entries(ours);
+    let theirs = entries(theirs);
+
+    ours.union(&theirs)
+        .filter(|entry| {
+            let removed =
+                base.contains(*entry) && !(ours.contains(*entry) && theirs.contains(*entry));
+            !removed
+        })
+        .cloned()
+        .collect()
+}
+
+/// Merge `base`, `ours` and `theirs`, writing the result to `ours`
+/// Matches git's merge driver convention (`%O %A %B`)
+pub fn merge_files(base: &Path, ours: &Path, theirs: &Path) -> Result<()> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))
+    };
+
+    let merged = merge_entries(&read(base)?, &read(ours)?, &read(theirs)?);
+
+    let mut content = merged.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    registry::write_atomic(ours, content.as_bytes())
+        .with_context(|| format!("Failed to write merged result to {:?}", ours))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_entries() {
+        let base = "anyhow-1.0.100.crate\nclap-4.5.54.crate\nsemver-1.0.27.crate\n";
+        // Ours adds serde and removes clap
+        let ours = "anyhow-1.0.100.crate\nsemver-1.0.27.crate\nserde-1.0.228.crate\n";
+        // Theirs adds toml (unsorted, with a duplicate and blank line)
+        let theirs = "toml-0.8.23.crate\nanyhow-1.0.100.crate\n\nclap-4.5.54.crate\nsemver-1.0.27.crate\ntoml-0.8.23.crate\n";
+
+        assert_eq!(
+            merge_entries(base, ours, theirs),
+            vec![
+                "anyhow-1.0.100.crate",
+                "semver-1.0.27.crate",
+                "serde-1.0.228.crate",
+                "toml-0.8.23.crate",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_entries_both_add_same() {
+        let base = "anyhow-1.0.100.crate\n";
+        let ours = "anyhow-1.0.100.crate\nserde-1.0.228.crate\n";
+        let theirs = "serde-1.0.228.crate\nanyhow-1.0.100.crate\n";
+
+        assert_eq!(
+            merge_entries(base, ours, theirs),
+            vec!["anyhow-1.0.100.crate", "serde-1.0.228.crate"]
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use crate::cancel;
+use crate::cargo;
+use crate::features;
+use crate::platform::Targets;
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+/// A resolved package from `cargo metadata`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Package {
+    pub id: String,
+    pub name: String,
+    pub version: Version,
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+    /// Where the package comes from, e.g. `registry+https://...` or `git+https://...`;
+    /// None for packages built from a local path
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Feature table of the package
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Dependencies as declared in the package's manifest
+    #[serde(default)]
+    pub dependencies: Vec<ManifestDependency>,
+}
+
+/// A dependency declared in a package's manifest
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestDependency {
+    pub name: String,
+    pub rename: Option<String>,
+    #[serde(default)]
+    pub optional: bool,
+    /// None for normal dependencies, "dev" or "build" otherwise
+    pub kind: Option<String>,
+}
+
+impl ManifestDependency {
+    /// Name the dependency goes by in the feature table
+    fn feature_name(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DepKind {
+    /// None for normal dependencies, "dev" or "build" otherwise
+    kind: Option<String>,
+    /// Platform the dependency is limited to, if any
+    target: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeDep {
+    /// Crate name the dependency is used under, with dashes turned into underscores
+    #[serde(default)]
+    name: String,
+    pkg: String,
+    dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    id: String,
+    deps: Vec<NodeDep>,
+    /// Features enabled on the package
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Resolve {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    resolve: Resolve,
+}
+
+/// The resolved dependency graph of a workspace, with normal edges only
+#[derive(Debug)]
+pub struct DependencyGraph {
+    packages: HashMap<String, Package>,
+    /// Package id -> ids of its normal dependencies on the selected targets
+    edges: HashMap<String, Vec<String>>,
+    /// (package id, dependency id) -> crate name the package uses the dependency under
+    dep_names: HashMap<(String, String), String>,
+    /// Package id -> features enabled on it
+    features: HashMap<String, Vec<String>>,
+    members: HashSet<String>,
+}
+
+impl DependencyGraph {
+    /// Resolve the workspace with `cargo metadata`
+    pub fn load(manifest_path: &Path, targets: &Targets, locked: bool) -> Result<DependencyGraph> {
+        let output = cancel::output(
+            cargo::command("metadata")
+                .arg("--manifest-path")
+                .arg(manifest_path)
+                .arg("--format-version")
+                .arg("1")
+                .args(locked.then_some("--locked")),
+            "cargo metadata",
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("cargo metadata failed: {}", stderr);
+        }
+
+        let metadata: Metadata =
+            serde_json::from_slice(&output.stdout).context("Could not parse cargo metadata")?;
+        Ok(DependencyGraph::from_metadata(metadata, targets))
+    }
+
+    fn from_metadata(metadata: Metadata, targets: &Targets) -> DependencyGraph {
+        let mut dep_names = HashMap::new();
+        let mut features = HashMap::new();
+        let edges = metadata
+            .resolve
+            .nodes
+            .into_iter()
+            .map(|node| {
+                let deps = node
+                    .deps
+                    .into_iter()
+                    .filter(|dep| {
+                        dep.dep_kinds.iter().any(|kind| {
+                            kind.kind.is_none()
+                                && kind.target.as_deref().is_none_or(|t| targets.matches(t))
+                        })
+                    })
+                    .map(|dep| {
+                        dep_names.insert((node.id.clone(), dep.pkg.clone()), dep.name);
+                        dep.pkg
+                    })
+                    .collect();
+                features.insert(node.id.clone(), node.features);
+                (node.id, deps)
+            })
+            .collect();
+
+        DependencyGraph {
+            packages: metadata
+                .packages
+                .into_iter()
+                .map(|package| (package.id.clone(), package))
+                .collect(),
+            edges,
+            dep_names,
+            features,
+            members: metadata.workspace_members.into_iter().collect(),
+        }
+    }
+
+    /// Find a package by name and version
+    pub fn package(&self, name: &str, version: &Version) -> Option<&Package> {
+        self.packages
+            .values()
+            .find(|p| p.name == name && &p.version == version)
+    }
+
+    /// License of a package: its SPDX expression, or a pointer to its license file
+    pub fn license(&self, name: &str, version: &Version) -> Option<String> {
+        let package = self.package(name, version)?;
+        package.license.clone().or_else(|| {
+            package
+                .license_file
+                .as_ref()
+                .map(|file| format!("see {}", file))
+        })
+    }
+
+    /// Ids of the package's normal dependencies on the selected targets
+    pub fn dependencies(&self, id: &str) -> &[String] {
+        self.edges.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn is_member(&self, id: &str) -> bool {
+        self.members.contains(id)
+    }
+
+    /// All resolved packages, in no particular order
+    pub fn packages(&self) -> impl Iterator<Item = &Package> {
+        self.packages.values()
+    }
+
+    /// Path dependencies outside the workspace, which never come from a registry
+    pub fn path_packages(&self) -> impl Iterator<Item = &Package> {
+        self.packages
+            .values()
+            .filter(|p| p.source.is_none() && !self.members.contains(&p.id))
+    }
+
+    /// Features enabled on the package
+    pub fn features(&self, id: &str) -> &[String] {
+        self.features.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Names of the packages depending on this one, when each of them only does so through
+    /// an optional dependency switched on by its `default` feature
+    ///
+    /// None when the package is a workspace member, has no dependents or at least one
+    /// dependent needs it regardless of its default features.
+    pub fn default_only_parents(&self, id: &str) -> Option<Vec<String>> {
+        let mut parents = BTreeSet::new();
+        for (parent_id, deps) in &self.edges {
+            if !deps.iter().any(|dep| dep == id) {
+                continue;
+            }
+            let parent = self.packages.get(parent_id)?;
+            let dep_name = self
+                .dep_names
+                .get(&(parent_id.clone(), id.to_string()))
+                .map(String::as_str)
+                .unwrap_or_default();
+            let declared: Vec<&ManifestDependency> = parent
+                .dependencies
+                .iter()
+                .filter(|dep| {
+                    dep.kind.is_none() && dep.feature_name().replace('-', "_") == dep_name
+                })
+                .collect();
+            if declared.is_empty() || declared.iter().any(|dep| !dep.optional) {
+                return None;
+            }
+
+            // Features enabled on the parent that `default` does not account for
+            let table = |feature: &str| parent.features.get(feature).map(Vec::as_slice);
+            let optional = |name: &str| {
+                parent
+                    .dependencies
+                    .iter()
+                    .any(|dep| dep.optional && dep.feature_name() == name)
+            };
+            let by_default = features::expand(table, optional, ["default".to_string()]);
+            let others = features::expand(
+                table,
+                optional,
+                self.features(parent_id)
+                    .iter()
+                    .filter(|feature| !by_default.features.contains(*feature))
+                    .cloned(),
+            );
+            if declared
+                .iter()
+                .any(|dep| !by_default.deps.contains(dep.feature_name()))
+                || declared
+                    .iter()
+                    .any(|dep| others.deps.contains(dep.feature_name()))
+            {
+                return None;
+            }
+            parents.insert(parent.name.clone());
+        }
+        (!parents.is_empty()).then(|| parents.into_iter().collect())
+    }
+
+    /// Packages reachable from the given workspace members through normal edges,
+    /// the members included, sorted by name and version
+    pub fn reachable(&self, member_names: &[&str]) -> Vec<&Package> {
+        let mut stack: Vec<&str> = self
+            .members
+            .iter()
+            .filter(|id| {
+                self.packages
+                    .get(*id)
+                    .is_some_and(|p| member_names.contains(&p.name.as_str()))
+            })
+            .map(String::as_str)
+            .collect();
+        let mut seen: HashSet<&str> = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if seen.insert(id) {
+                stack.extend(self.dependencies(id).iter().map(String::as_str));
+            }
+        }
+
+        let mut packages: Vec<&Package> = seen
+            .into_iter()
+            .filter_map(|id| self.packages.get(id))
+            .collect();
+        packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        packages
+    }
+
+    /// Direct dependencies of the given workspace members that pull in the package
+    ///
+    /// Dependencies between workspace members are followed, so the result names
+    /// the external crates a developer actually declared
+    pub fn introduced_by(
+        &self,
+        member_names: &[&str],
+        name: &str,
+        version: &Version,
+    ) -> Vec<String> {
+        let Some(target) = self.package(name, version) else {
+            return Vec::new();
+        };
+
+        // External crates reachable from the members through member-to-member edges
+        let mut direct: BTreeSet<&str> = BTreeSet::new();
+        let mut queue: VecDeque<&str> = self
+            .members
+            .iter()
+            .filter(|id| {
+                self.packages
+                    .get(*id)
+                    .is_some_and(|p| member_names.contains(&p.name.as_str()))
+            })
+            .map(String::as_str)
+            .collect();
+        let mut seen: HashSet<&str> = queue.iter().copied().collect();
+        while let Some(id) = queue.pop_front() {
+            for dep in self.dependencies(id) {
+                if self.members.contains(dep) {
+                    if seen.insert(dep) {
+                        queue.push_back(dep);
+                    }
+                } else {
+                    direct.insert(dep);
+                }
+            }
+        }
+
+        direct
+            .into_iter()
+            .filter(|id| self.reaches(id, &target.id))
+            .filter_map(|id| self.packages.get(id).map(|p| p.name.clone()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Whether `to` is `from` or one of its transitive dependencies
+    fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut stack = vec![from];
+        let mut seen: HashSet<&str> = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            if seen.insert(id) {
+                stack.extend(self.dependencies(id).iter().map(String::as_str));
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::ALL_TARGETS;
+    use crate::platform::tests::linux;
+
+    fn sample_graph(targets: &Targets) -> DependencyGraph {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "packages": [
+                    {"id": "app", "name": "app", "version": "0.1.0", "license": null, "license_file": null},
+                    {"id": "core", "name": "core-lib", "version": "0.1.0", "license": null, "license_file": null},
+                    {"id": "tokio", "source": "registry+https://github.com/rust-lang/crates.io-index", "name": "tokio", "version": "1.40.0", "license": "MIT", "license_file": null},
+                    {"id": "reqwest", "source": "registry+https://github.com/rust-lang/crates.io-index", "name": "reqwest", "version": "0.12.0", "license": "MIT OR Apache-2.0", "license_file": null},
+                    {"id": "mio", "source": "registry+https://github.com/rust-lang/crates.io-index", "name": "mio", "version": "1.0.0", "license": null, "license_file": "LICENSE"},
+                    {"id": "winapi", "source": "registry+https://github.com/rust-lang/crates.io-index", "name": "winapi", "version": "0.3.9", "license": "MIT", "license_file": null},
+                    {"id": "criterion", "source": "registry+https://github.com/rust-lang/crates.io-index", "name": "criterion", "version": "0.5.0", "license": "MIT", "license_file": null},
+                    {"id": "vendored", "name": "vendored-zlib", "version": "0.1.0", "license": null, "license_file": null}
+                ],
+                "workspace_members": ["app", "core"],
+                "resolve": {"nodes": [
+                    {"id": "app", "deps": [
+                        {"pkg": "core", "dep_kinds": [{"kind": null, "target": null}]},
+                        {"pkg": "reqwest", "dep_kinds": [{"kind": null, "target": null}]},
+                        {"pkg": "criterion", "dep_kinds": [{"kind": "dev", "target": null}]}
+                    ]},
+                    {"id": "core", "deps": [
+                        {"pkg": "tokio", "dep_kinds": [{"kind": null, "target": null}]}
+                    ]},
+                    {"id": "reqwest", "deps": [
+                        {"pkg": "tokio", "dep_kinds": [{"kind": null, "target": null}]}
+                    ]},
+                    {"id": "tokio", "deps": [
+                        {"pkg": "mio", "dep_kinds": [{"kind": null, "target": null}]},
+                        {"pkg": "winapi", "dep_kinds": [{"kind": null, "target": "cfg(windows)"}]}
+                    ]},
+                    {"id": "mio", "deps": []},
+                    {"id": "winapi", "deps": []},
+                    {"id": "criterion", "deps": []}
+                ]}
+            }"#,
+        )
+        .unwrap();
+        DependencyGraph::from_metadata(metadata, targets)
+    }
+
+    fn version(v: &str) -> Version {
+        Version::parse(v).unwrap()
+    }
+
+    #[test]
+    fn test_introduced_by() {
+        let graph = sample_graph(&Targets::load(&[ALL_TARGETS.to_string()]).unwrap());
+
+        // Pulled in directly by core-lib and through reqwest
+        assert_eq!(
+            graph.introduced_by(&["app"], "mio", &version("1.0.0")),
+            vec!["reqwest", "tokio"]
+        );
+        assert_eq!(
+            graph.introduced_by(&["app"], "reqwest", &version("0.12.0")),
+            vec!["reqwest"]
+        );
+        // Dev dependencies are not followed
+        assert!(
+            graph
+                .introduced_by(&["app"], "criterion", &version("0.5.0"))
+                .is_empty()
+        );
+        // Only through core-lib when checking just that member
+        assert_eq!(
+            graph.introduced_by(&["core-lib"], "mio", &version("1.0.0")),
+            vec!["tokio"]
+        );
+        assert_eq!(
+            graph.introduced_by(&["app"], "winapi", &version("0.3.9")),
+            vec!["reqwest", "tokio"]
+        );
+    }
+
+    #[test]
+    fn test_reachable() {
+        let graph = sample_graph(&linux());
+
+        let names = |members: &[&str]| -> Vec<String> {
+            graph
+                .reachable(members)
+                .iter()
+                .map(|p| p.name.clone())
+                .collect()
+        };
+        // No dev dependencies and no Windows-only crates
+        assert_eq!(
+            names(&["app"]),
+            vec!["app", "core-lib", "mio", "reqwest", "tokio"]
+        );
+        assert_eq!(names(&["core-lib"]), vec!["core-lib", "mio", "tokio"]);
+        assert!(graph.is_member("core"));
+        assert!(!graph.is_member("tokio"));
+
+        // Members have no source either, but they are checked separately
+        let paths: Vec<&str> = graph.path_packages().map(|p| p.id.as_str()).collect();
+        assert_eq!(paths, vec!["vendored"]);
+    }
+
+    #[test]
+    fn test_platform_edges_and_license() {
+        let graph = sample_graph(&linux());
+
+        assert!(
+            graph
+                .introduced_by(&["app"], "winapi", &version("0.3.9"))
+                .is_empty()
+        );
+
+        assert_eq!(
+            graph.license("reqwest", &version("0.12.0")).as_deref(),
+            Some("MIT OR Apache-2.0")
+        );
+        assert_eq!(
+            graph.license("mio", &version("1.0.0")).as_deref(),
+            Some("see LICENSE")
+        );
+        assert_eq!(graph.license("mio", &version("9.9.9")), None);
+    }
+
+    #[test]
+    fn test_default_only_parents() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "packages": [
+                    {"id": "app", "name": "app", "version": "0.1.0", "license": null, "license_file": null,
+                     "dependencies": [{"name": "hyper", "rename": null, "optional": false, "kind": null}]},
+                    {"id": "hyper", "name": "hyper", "version": "1.0.0", "license": null, "license_file": null,
+                     "features": {"default": ["http1", "tls"], "http1": [], "tls": ["dep:rustls"], "unstable": []},
+                     "dependencies": [
+                        {"name": "rustls", "rename": null, "optional": true, "kind": null},
+                        {"name": "serde", "rename": null, "optional": true, "kind": null}
+                     ]},
+                    {"id": "rustls", "name": "rustls", "version": "0.23.0", "license": null, "license_file": null},
+                    {"id": "serde", "name": "serde", "version": "1.0.0", "license": null, "license_file": null}
+                ],
+                "workspace_members": ["app"],
+                "resolve": {"nodes": [
+                    {"id": "app", "features": [], "deps": [
+                        {"name": "hyper", "pkg": "hyper", "dep_kinds": [{"kind": null, "target": null}]}
+                    ]},
+                    {"id": "hyper", "features": ["default", "http1", "serde", "tls"], "deps": [
+                        {"name": "rustls", "pkg": "rustls", "dep_kinds": [{"kind": null, "target": null}]},
+                        {"name": "serde", "pkg": "serde", "dep_kinds": [{"kind": null, "target": null}]}
+                    ]},
+                    {"id": "rustls", "features": [], "deps": []},
+                    {"id": "serde", "features": [], "deps": []}
+                ]}
+            }"#,
+        )
+        .unwrap();
+        let graph = DependencyGraph::from_metadata(metadata, &linux());
+
+        assert_eq!(
+            graph.features("hyper"),
+            &["default", "http1", "serde", "tls"]
+        );
+        // Only the default "tls" feature of hyper pulls in rustls
+        assert_eq!(
+            graph.default_only_parents("rustls"),
+            Some(vec!["hyper".to_string()])
+        );
+        // serde is enabled explicitly (as an implicit feature), hyper is a plain dependency
+        assert_eq!(graph.default_only_parents("serde"), None);
+        assert_eq!(graph.default_only_parents("hyper"), None);
+        assert_eq!(graph.default_only_parents("app"), None);
+    }
+}
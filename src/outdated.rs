@@ -0,0 +1,237 @@
+use crate::index::{self, Index};
+use crate::registry::Registry;
+use crate::registry_versions;
+use crate::report::csv_field;
+use anyhow::Result;
+use semver::Version;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// How far a registry crate is behind the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lag {
+    UpToDate,
+    Patch,
+    Minor,
+    /// The latest version is semver-incompatible (a new major, or a new minor of a 0.x crate)
+    Major,
+    /// The index snapshot does not list the crate
+    NotInIndex,
+}
+
+impl Lag {
+    fn between(current: &Version, latest: &Version) -> Lag {
+        if latest <= current {
+            Lag::UpToDate
+        } else if latest.major != current.major
+            || (current.major == 0 && latest.minor != current.minor)
+        {
+            Lag::Major
+        } else if latest.minor != current.minor {
+            Lag::Minor
+        } else {
+            Lag::Patch
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lag::UpToDate => "up-to-date",
+            Lag::Patch => "patch",
+            Lag::Minor => "minor",
+            Lag::Major => "major",
+            Lag::NotInIndex => "not-in-index",
+        }
+    }
+}
+
+/// Newest registry version of a crate compared to the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outdated {
+    pub name: String,
+    pub registry: Version,
+    pub latest: Option<Version>,
+    pub lag: Lag,
+    /// Stable, unyanked releases newer than the registry version
+    pub releases_behind: usize,
+}
+
+/// Compare the newest registry version of every crate to the index, most behind first
+pub fn compare(registry: &Registry, index: &Index) -> Result<Vec<Outdated>> {
+    let mut outdated = Vec::new();
+
+    for (name, versions) in registry_versions(&registry.entries) {
+        let Some(current) = versions.last() else {
+            continue;
+        };
+        let published = index.versions(&name)?;
+        let latest = index::latest(&published).map(|v| v.vers.clone());
+        let lag = match &latest {
+            Some(latest) => Lag::between(current, latest),
+            None => Lag::NotInIndex,
+        };
+        let releases_behind = published
+            .iter()
+            .filter(|v| !v.yanked && v.vers.pre.is_empty() && v.vers > *current)
+            .count();
+
+        outdated.push(Outdated {
+            name,
+            registry: current.clone(),
+            latest,
+            lag,
+            releases_behind,
+        });
+    }
+
+    outdated.sort_by(|a, b| b.lag.cmp(&a.lag).then_with(|| a.name.cmp(&b.name)));
+    Ok(outdated)
+}
+
+fn count(outdated: &[Outdated], lag: Lag) -> usize {
+    outdated.iter().filter(|o| o.lag == lag).count()
+}
+
+fn render_text(outdated: &[Outdated], out: &mut impl Write) -> io::Result<()> {
+    let behind: Vec<&Outdated> = outdated.iter().filter(|o| o.lag != Lag::UpToDate).collect();
+
+    if behind.is_empty() {
+        writeln!(
+            out,
+            "All {} registry crates are up to date.",
+            outdated.len()
+        )?;
+        return Ok(());
+    }
+
+    let width = behind.iter().map(|o| o.name.len()).max().unwrap_or(0);
+    for o in &behind {
+        match &o.latest {
+            Some(latest) => writeln!(
+                out,
+                "  {:width$}  {} -> {}  {} ({} release(s) behind)",
+                o.name,
+                o.registry,
+                latest,
+                o.lag.as_str(),
+                o.releases_behind
+            )?,
+            None => writeln!(out, "  {:width$}  {}  not in the index", o.name, o.registry)?,
+        }
+    }
+
+    writeln!(
+        out,
+        "\n{} of {} registry crates are behind the index ({} major, {} minor, {} patch); {} not in the index",
+        behind.len() - count(outdated, Lag::NotInIndex),
+        outdated.len(),
+        count(outdated, Lag::Major),
+        count(outdated, Lag::Minor),
+        count(outdated, Lag::Patch),
+        count(outdated, Lag::NotInIndex)
+    )?;
+    Ok(())
+}
+
+fn render_csv(outdated: &[Outdated], out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "crate,registry_version,latest_version,lag,releases_behind"
+    )?;
+    for o in outdated {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            csv_field(&o.name),
+            o.registry,
+            o.latest.as_ref().map(|v| v.to_string()).unwrap_or_default(),
+            o.lag.as_str(),
+            o.releases_behind
+        )?;
+    }
+    Ok(())
+}
+
+/// Print how far each registry crate is behind the index snapshot
+pub fn print_outdated(registry_file: &Path, index_dir: &Path, csv: bool) -> Result<()> {
+    let registry = Registry::load(registry_file)?;
+    let index = Index::open(index_dir)?;
+    let outdated = compare(&registry, &index)?;
+
+    let mut out = io::stdout().lock();
+    if csv {
+        render_csv(&outdated, &mut out)?;
+    } else {
+        render_text(&outdated, &mut out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_lag_between() {
+        let v = |s: &str| Version::parse(s).unwrap();
+        assert_eq!(Lag::between(&v("1.0.195"), &v("1.0.228")), Lag::Patch);
+        assert_eq!(Lag::between(&v("1.2.0"), &v("1.5.1")), Lag::Minor);
+        assert_eq!(Lag::between(&v("1.41.0"), &v("2.0.0")), Lag::Major);
+        assert_eq!(Lag::between(&v("0.3.1"), &v("0.4.0")), Lag::Major);
+        assert_eq!(Lag::between(&v("1.0.228"), &v("1.0.228")), Lag::UpToDate);
+        // A snapshot older than the registry is not "behind"
+        assert_eq!(Lag::between(&v("1.0.229"), &v("1.0.228")), Lag::UpToDate);
+    }
+
+    #[test]
+    fn test_compare() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("se/rd")).unwrap();
+        fs::write(
+            dir.join("se/rd/serde"),
+            "{\"name\":\"serde\",\"vers\":\"1.0.200\"}\n\
+             {\"name\":\"serde\",\"vers\":\"1.0.210\"}\n\
+             {\"name\":\"serde\",\"vers\":\"1.0.228\"}\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.join("3/s")).unwrap();
+        fs::write(
+            dir.join("3/s/syn"),
+            "{\"name\":\"syn\",\"vers\":\"2.0.0\"}\n",
+        )
+        .unwrap();
+
+        let registry_file = dir.join("registry.txt");
+        fs::write(
+            &registry_file,
+            "serde-1.0.100.crate\nserde-1.0.200.crate\nsyn-2.0.0.crate\ninternal-0.1.0.crate\n",
+        )
+        .unwrap();
+
+        let registry = Registry::load(&registry_file).unwrap();
+        let outdated = compare(&registry, &Index::open(dir).unwrap()).unwrap();
+
+        let rows: Vec<(&str, Lag, usize)> = outdated
+            .iter()
+            .map(|o| (o.name.as_str(), o.lag, o.releases_behind))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("internal", Lag::NotInIndex, 0),
+                ("serde", Lag::Patch, 2),
+                ("syn", Lag::UpToDate, 0),
+            ]
+        );
+
+        let mut text = Vec::new();
+        render_text(&outdated, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("serde     1.0.200 -> 1.0.228  patch (2 release(s) behind)"));
+        assert!(text.contains(
+            "1 of 3 registry crates are behind the index (0 major, 0 minor, 1 patch); 1 not in the index"
+        ));
+    }
+}
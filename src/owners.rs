@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Owner recorded for crates that no ownership rule matches
+pub const UNOWNED: &str = "unowned";
+
+/// Crate ownership rules, in CODEOWNERS style
+///
+/// Each non-comment line is `<pattern> <owner>...`, where the pattern matches
+/// crate names and may use `*` and `?` wildcards. The last matching line wins.
+#[derive(Debug, Default)]
+pub struct Owners {
+    rules: Vec<(String, String)>,
+}
+
+impl Owners {
+    pub fn load(path: &Path) -> Result<Owners> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not read ownership file {:?}", path))?;
+        Owners::parse(&content)
+    }
+
+    fn parse(content: &str) -> Result<Owners> {
+        let mut rules = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next().unwrap_or_default();
+            let owners: Vec<&str> = parts.collect();
+            if owners.is_empty() {
+                anyhow::bail!(
+                    "Ownership rule on line {} has no owner: {}",
+                    index + 1,
+                    line
+                );
+            }
+
+            rules.push((pattern.to_string(), owners.join(" ")));
+        }
+
+        Ok(Owners { rules })
+    }
+
+    /// Owner of a crate, or None if no rule matches
+    pub fn owner_of(&self, crate_name: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| glob_match(pattern, crate_name))
+            .map(|(_, owner)| owner.as_str())
+    }
+}
+
+/// Match a name against a pattern with `*` (any run of characters) and `?` (one character)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last '*' and the name position it currently covers up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, covered)) = backtrack {
+            // Let the last '*' swallow one more character
+            p = star + 1;
+            n = covered + 1;
+            backtrack = Some((star, covered + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("serde", "serde"));
+        assert!(!glob_match("serde", "serde_json"));
+        assert!(glob_match("serde*", "serde_json"));
+        assert!(glob_match("*-sys", "openssl-sys"));
+        assert!(!glob_match("*-sys", "openssl"));
+        assert!(glob_match("tokio-*-?", "tokio-util-x"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_owner_of() {
+        let owners = Owners::parse(
+            "# Default owner for everything\n\
+             * @platform-team\n\
+             \n\
+             tokio* @async-team\n\
+             *-sys @ffi-team @security\n",
+        )
+        .unwrap();
+
+        assert_eq!(owners.owner_of("serde"), Some("@platform-team"));
+        assert_eq!(owners.owner_of("tokio-util"), Some("@async-team"));
+        // Last match wins
+        assert_eq!(owners.owner_of("openssl-sys"), Some("@ffi-team @security"));
+
+        let owners = Owners::parse("tokio* @async-team\n").unwrap();
+        assert_eq!(owners.owner_of("serde"), None);
+
+        assert!(Owners::parse("serde\n").is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::promote;
+use crate::registry::{Registry, RegistryFormat};
+use anyhow::Result;
+use std::path::Path;
+
+/// The pending listing, or an empty one in the format its name asks for
+fn load_or_new(pending_file: &Path) -> Result<Registry> {
+    if pending_file.exists() {
+        Registry::load(pending_file)
+    } else {
+        Ok(Registry::new(RegistryFormat::from_path(pending_file)))
+    }
+}
+
+/// Add crate files to the pending-approval listing instead of the registry
+///
+/// Returns the entries that were not pending yet.
+pub fn stage(pending_file: &Path, crate_files: &[String]) -> Result<Vec<String>> {
+    let mut pending = load_or_new(pending_file)?;
+    let mut staged: Vec<String> = crate_files
+        .iter()
+        .filter(|crate_file| pending.entries.insert(crate_file.to_string()))
+        .cloned()
+        .collect();
+    staged.sort();
+    pending.save(pending_file)?;
+    Ok(staged)
+}
+
+/// Move reviewed entries from the pending listing into the registry
+///
+/// `crates` are crate files or names (all pending versions), like `promote`; with `all`,
+/// everything still pending is approved. Applied entries leave the pending listing.
+pub fn apply_pending(
+    pending_file: &Path,
+    registry_file: &Path,
+    crates: &[String],
+    all: bool,
+) -> Result<()> {
+    let mut pending = Registry::load(pending_file)?;
+    let mut registry = Registry::load(registry_file)?;
+
+    let approved = promote::select_entries(&pending, crates, all)?;
+    if approved.is_empty() {
+        eprintln!("Nothing is pending in {:?}.", pending_file);
+        return Ok(());
+    }
+
+    let mut applied = 0;
+    for crate_file in &approved {
+        if registry.entries.contains(crate_file) {
+            eprintln!("{} is already in the registry", crate_file);
+        } else {
+            registry.insert_from(&pending, crate_file);
+            println!("Applied {}", crate_file);
+            applied += 1;
+        }
+    }
+    let mut remaining = Registry::new(RegistryFormat::from_path(pending_file));
+    for crate_file in pending.entries.clone() {
+        if !approved.contains(&crate_file) {
+            remaining.insert_from(&pending, &crate_file);
+        }
+    }
+    pending = remaining;
+
+    // The registry first: should the second write fail, entries are pending twice, never lost
+    registry.save(registry_file)?;
+    pending.save(pending_file)?;
+    eprintln!(
+        "Applied {} entries to {:?}; {} still pending",
+        applied,
+        registry_file,
+        pending.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_stage_and_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let registry_file = dir.join("registry.txt");
+        let pending_file = dir.join("pending.txt");
+        fs::write(&registry_file, "anyhow-1.0.100.crate\n").unwrap();
+
+        let first = stage(
+            &pending_file,
+            &[
+                "serde-1.0.228.crate".to_string(),
+                "tokio-1.40.0.crate".to_string(),
+            ],
+        )
+        .unwrap();
+        let second = stage(
+            &pending_file,
+            &[
+                "serde-1.0.228.crate".to_string(),
+                "serde-1.0.200.crate".to_string(),
+            ],
+        )
+        .unwrap();
+        let staged = fs::read_to_string(&pending_file).unwrap();
+
+        // tokio was rejected in review: only serde (every pending version) is applied
+        apply_pending(&pending_file, &registry_file, &["serde".to_string()], false).unwrap();
+        let registry = fs::read_to_string(&registry_file).unwrap();
+        let pending = fs::read_to_string(&pending_file).unwrap();
+        let unknown = apply_pending(&pending_file, &registry_file, &["rand".to_string()], false);
+
+        assert_eq!(first, vec!["serde-1.0.228.crate", "tokio-1.40.0.crate"]);
+        assert_eq!(second, vec!["serde-1.0.200.crate"]);
+        assert_eq!(
+            staged,
+            "serde-1.0.200.crate\nserde-1.0.228.crate\ntokio-1.40.0.crate\n"
+        );
+        assert_eq!(
+            registry,
+            "anyhow-1.0.100.crate\nserde-1.0.200.crate\nserde-1.0.228.crate\n"
+        );
+        assert_eq!(pending, "tokio-1.40.0.crate\n");
+        assert!(unknown.is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use crate::index::Index;
+use crate::outdated::{self, Lag};
+use crate::registry::Registry;
+use crate::registry_versions;
+use crate::report::csv_field;
+use crate::trend::{self, Point};
+use anyhow::Result;
+use semver::{Version, VersionReq};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A proposed registry upgrade for the next transfer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upgrade {
+    pub name: String,
+    pub from: Version,
+    pub to: Version,
+    pub lag: Lag,
+    pub releases_behind: usize,
+    /// Projects whose latest archived report needs a version the upgrade provides
+    pub projects: Vec<String>,
+    /// Registry crates whose newest release requires the upgraded version
+    pub dependents: Vec<String>,
+}
+
+impl Upgrade {
+    fn score(&self) -> usize {
+        self.projects.len() + self.dependents.len()
+    }
+}
+
+/// Does the requirement accept `version`; unparseable requirements never do
+fn req_matches(req: &str, version: &Version) -> bool {
+    VersionReq::parse(req).is_ok_and(|req| req.matches(version))
+}
+
+/// Score every crate that is behind the index
+fn candidates(
+    registry: &Registry,
+    index: &Index,
+    history: &BTreeMap<String, Vec<Point>>,
+) -> Result<Vec<Upgrade>> {
+    let versions = registry_versions(&registry.entries);
+
+    // Dependencies of the newest published release of every registry crate
+    let mut latest_deps = Vec::new();
+    for name in versions.keys() {
+        if let Some(latest) = crate::index::latest(&index.versions(name)?) {
+            latest_deps.push((name.clone(), latest.deps.clone()));
+        }
+    }
+
+    let mut upgrades = Vec::new();
+    for o in outdated::compare(registry, index)? {
+        let Some(to) = o.latest else {
+            continue;
+        };
+        if o.lag == Lag::UpToDate {
+            continue;
+        }
+        let current = versions.get(&o.name).map(Vec::as_slice).unwrap_or_default();
+
+        let projects = history
+            .iter()
+            .filter(|(_, points)| {
+                points.last().is_some_and(|point| {
+                    point
+                        .demands
+                        .iter()
+                        .any(|(name, req)| *name == o.name && req_matches(req, &to))
+                })
+            })
+            .map(|(project, _)| project.clone())
+            .collect();
+
+        let dependents = latest_deps
+            .iter()
+            .filter(|(_, deps)| {
+                deps.iter().any(|dep| {
+                    !dep.is_dev()
+                        && dep.crate_name() == o.name
+                        && req_matches(&dep.req, &to)
+                        && !current.iter().any(|v| req_matches(&dep.req, v))
+                })
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        upgrades.push(Upgrade {
+            name: o.name,
+            from: o.registry,
+            to,
+            lag: o.lag,
+            releases_behind: o.releases_behind,
+            projects,
+            dependents,
+        });
+    }
+
+    Ok(upgrades)
+}
+
+/// Pick the `budget` most valuable upgrades: most projects and dependents unblocked first,
+/// then the furthest behind
+fn plan(mut upgrades: Vec<Upgrade>, budget: usize) -> Vec<Upgrade> {
+    upgrades.sort_by(|a, b| {
+        b.score()
+            .cmp(&a.score())
+            .then_with(|| b.lag.cmp(&a.lag))
+            .then_with(|| b.releases_behind.cmp(&a.releases_behind))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    upgrades.truncate(budget);
+    upgrades
+}
+
+fn render_text(
+    plan: &[Upgrade],
+    candidates: usize,
+    budget: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if plan.is_empty() {
+        writeln!(out, "Nothing to refresh: the registry is up to date.")?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "Refresh plan: {} of {} crates behind the index (budget {}):",
+        plan.len(),
+        candidates,
+        budget
+    )?;
+    for (rank, upgrade) in plan.iter().enumerate() {
+        writeln!(
+            out,
+            "{:4}. {} {} -> {}  {}, {} project(s), {} dependent(s)",
+            rank + 1,
+            upgrade.name,
+            upgrade.from,
+            upgrade.to,
+            upgrade.lag.as_str(),
+            upgrade.projects.len(),
+            upgrade.dependents.len()
+        )?;
+        if !upgrade.projects.is_empty() {
+            writeln!(out, "      projects: {}", upgrade.projects.join(", "))?;
+        }
+        if !upgrade.dependents.is_empty() {
+            writeln!(out, "      needed by: {}", upgrade.dependents.join(", "))?;
+        }
+    }
+    Ok(())
+}
+
+fn render_csv(plan: &[Upgrade], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "rank,crate,from,to,lag,projects,dependents")?;
+    for (rank, upgrade) in plan.iter().enumerate() {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            rank + 1,
+            csv_field(&upgrade.name),
+            upgrade.from,
+            upgrade.to,
+            upgrade.lag.as_str(),
+            csv_field(&upgrade.projects.join("; ")),
+            csv_field(&upgrade.dependents.join("; "))
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the most valuable upgrades to pull in the next transfer
+pub fn print_plan(
+    registry_file: &Path,
+    index_dir: &Path,
+    budget: usize,
+    report_dir: Option<&Path>,
+    csv: bool,
+) -> Result<()> {
+    let registry = Registry::load(registry_file)?;
+    let index = Index::open(index_dir)?;
+    let history = match report_dir {
+        Some(report_dir) => trend::load_history(report_dir)?,
+        None => BTreeMap::new(),
+    };
+
+    let candidates = candidates(&registry, &index, &history)?;
+    let count = candidates.len();
+    let plan = plan(candidates, budget);
+
+    let mut out = io::stdout().lock();
+    if csv {
+        render_csv(&plan, &mut out)?;
+    } else {
+        render_text(&plan, count, budget, &mut out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_plan_refresh() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let index_files = [
+            ("se/rd/serde", "{\"name\":\"serde\",\"vers\":\"1.0.228\"}\n"),
+            ("to/ki/tokio", "{\"name\":\"tokio\",\"vers\":\"1.47.0\"}\n"),
+            ("3/l/log", "{\"name\":\"log\",\"vers\":\"0.4.28\"}\n"),
+            // The newest serde_json needs a newer serde than the registry has
+            (
+                "se/rd/serde_json",
+                "{\"name\":\"serde_json\",\"vers\":\"1.0.140\",\"deps\":[\
+                 {\"name\":\"serde\",\"req\":\"^1.0.220\",\"kind\":\"normal\"},\
+                 {\"name\":\"tokio\",\"req\":\"^1.47\",\"kind\":\"dev\"}]}\n",
+            ),
+        ];
+        for (path, content) in index_files {
+            let path = dir.join("index").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let registry_file = dir.join("registry.txt");
+        fs::write(
+            &registry_file,
+            "serde-1.0.200.crate\ntokio-1.40.0.crate\nlog-0.4.20.crate\nserde_json-1.0.140.crate\n",
+        )
+        .unwrap();
+
+        let registry = Registry::load(&registry_file).unwrap();
+        let index = Index::open(&dir.join("index")).unwrap();
+        let mut history = BTreeMap::new();
+        history.insert(
+            "api".to_string(),
+            vec![Point {
+                time: chrono::NaiveDateTime::default(),
+                missing: 1,
+                registry_size: None,
+                demands: vec![("tokio".to_string(), "^1.45".to_string())],
+            }],
+        );
+
+        let candidates = candidates(&registry, &index, &history).unwrap();
+        assert_eq!(candidates.len(), 3);
+
+        let plan = plan(candidates, 2);
+        let picked: Vec<(&str, &[String], &[String])> = plan
+            .iter()
+            .map(|u| (u.name.as_str(), &u.projects[..], &u.dependents[..]))
+            .collect();
+        assert_eq!(
+            picked,
+            vec![
+                // Equal value: the minor upgrade goes before the patch upgrade
+                ("tokio", &["api".to_string()][..], &[][..]),
+                ("serde", &[][..], &["serde_json".to_string()][..]),
+            ]
+        );
+
+        // Project names are free text
+        let mut plan = plan;
+        plan[0].projects.push("legacy, v1".to_string());
+        let mut csv = Vec::new();
+        render_csv(&plan, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(
+            csv.lines()
+                .nth(1)
+                .unwrap()
+                .ends_with(",\"api; legacy, v1\",")
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::cancel;
+use anyhow::{Context, Result};
+use cargo_platform::{Cfg, Platform};
+use std::process::Command;
+use std::str::FromStr;
+
+/// Special `--target` value that selects every platform, like `cargo tree --target all`
+pub const ALL_TARGETS: &str = "all";
+
+/// Target platforms dependencies are resolved for
+/// Used to decide which `[target.'<spec>'.dependencies]` sections apply
+#[derive(Debug)]
+pub struct Targets {
+    /// Target triples with their cfg values; empty when all targets are selected
+    targets: Vec<(String, Vec<Cfg>)>,
+}
+
+/// Parse `rustc --print cfg` output into cfg values
+fn parse_cfg_output(output: &str) -> Result<Vec<Cfg>> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| Cfg::from_str(line).with_context(|| format!("Invalid cfg: {}", line)))
+        .collect()
+}
+
+/// Ask rustc for the cfg values of a target triple
+fn target_cfgs(triple: &str) -> Result<Vec<Cfg>> {
+    let output = cancel::output(
+        Command::new("rustc")
+            .arg("--print")
+            .arg("cfg")
+            .arg("--target")
+            .arg(triple),
+        "rustc",
+    )?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("rustc --print cfg failed for target {}: {}", triple, stderr);
+    }
+
+    parse_cfg_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Host triple reported by `rustc -vV`, which cargo uses when no target is given
+fn host_triple() -> Result<String> {
+    let output = cancel::output(Command::new("rustc").arg("-vV"), "rustc")?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(|host| host.trim().to_string())
+        .context("Could not determine host triple from rustc -vV")
+}
+
+impl Targets {
+    /// Targets for the given `--target` values; the host when none are given
+    pub fn load(triples: &[String]) -> Result<Targets> {
+        if triples.iter().any(|t| t == ALL_TARGETS) {
+            return Ok(Targets {
+                targets: Vec::new(),
+            });
+        }
+
+        let triples = if triples.is_empty() {
+            vec![host_triple()?]
+        } else {
+            triples.to_vec()
+        };
+
+        let targets = triples
+            .into_iter()
+            .map(|triple| {
+                let cfgs = target_cfgs(&triple)?;
+                Ok((triple, cfgs))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Targets { targets })
+    }
+
+    /// Whether a `[target.<spec>]` section applies to any selected target
+    /// Specs that can't be parsed are kept, so nothing is silently dropped
+    pub fn matches(&self, spec: &str) -> bool {
+        if self.targets.is_empty() {
+            return true;
+        }
+
+        match Platform::from_str(spec) {
+            Ok(platform) => self
+                .targets
+                .iter()
+                .any(|(triple, cfgs)| platform.matches(triple, cfgs)),
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// x86_64 Linux built from known cfg values, so tests don't need rustc
+    pub(crate) fn linux() -> Targets {
+        let cfgs = parse_cfg_output(
+            "debug_assertions\n\
+             panic=\"unwind\"\n\
+             target_arch=\"x86_64\"\n\
+             target_env=\"gnu\"\n\
+             target_family=\"unix\"\n\
+             target_os=\"linux\"\n\
+             target_pointer_width=\"64\"\n\
+             unix\n",
+        )
+        .unwrap();
+        Targets {
+            targets: vec![("x86_64-unknown-linux-gnu".to_string(), cfgs)],
+        }
+    }
+
+    #[test]
+    fn test_targets_matches() {
+        let targets = linux();
+
+        assert!(targets.matches("cfg(unix)"));
+        assert!(targets.matches("cfg(target_os = \"linux\")"));
+        assert!(targets.matches("cfg(any(windows, target_os = \"linux\"))"));
+        assert!(targets.matches("x86_64-unknown-linux-gnu"));
+
+        assert!(!targets.matches("cfg(windows)"));
+        assert!(!targets.matches("cfg(target_os = \"macos\")"));
+        assert!(!targets.matches("cfg(not(unix))"));
+        assert!(!targets.matches("x86_64-pc-windows-msvc"));
+
+        // Unparseable specs are kept
+        assert!(targets.matches("cfg(("));
+    }
+
+    #[test]
+    fn test_all_targets_match_everything() {
+        let targets = Targets::load(&[ALL_TARGETS.to_string()]).unwrap();
+        assert!(targets.matches("cfg(windows)"));
+        assert!(targets.matches("wasm32-unknown-unknown"));
+    }
+}
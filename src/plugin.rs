@@ -0,0 +1,106 @@
+use crate::report::{self, Report};
+use crate::trace;
+use anyhow::{Context, Result};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Run a single plugin, feeding it the JSON report on stdin
+/// The plugin's stdout and stderr both go to our stderr, so whatever it prints never mixes
+/// with a report or listing on stdout
+/// Returns whether the plugin exited successfully
+fn run_plugin(plugin: &Path, report_json: &[u8]) -> Result<bool> {
+    let mut child = Command::new(plugin)
+        .stdin(Stdio::piped())
+        .stdout(io::stderr())
+        .spawn()
+        .with_context(|| format!("Failed to start plugin {:?}", plugin))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A plugin that doesn't care about the report may exit without reading it
+        match stdin.write_all(report_json) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+                return Err(e).with_context(|| format!("Failed to send report to {:?}", plugin));
+            }
+            _ => {}
+        }
+    }
+
+    let status = child
+        .wait()
+        .with_context(|| format!("Failed to wait for plugin {:?}", plugin))?;
+
+    Ok(status.success())
+}
+
+/// Run every plugin against the report
+/// All plugins run even if an earlier one fails; any failure fails the check
+pub fn run_plugins(plugins: &[PathBuf], report: &Report, schema_version: u32) -> Result<()> {
+    if plugins.is_empty() {
+        return Ok(());
+    }
+
+    let report_json = serde_json::to_vec(&report::to_json(report, schema_version)?)
+        .context("Failed to serialize report")?;
+
+    let mut failed: Vec<String> = Vec::new();
+    for plugin in plugins {
+        eprintln!("Running plugin {:?}...", plugin);
+        let passed = trace::span("plugin", &[("path", &plugin.display())], || {
+            run_plugin(plugin, &report_json)
+        })?;
+        if !passed {
+            failed.push(plugin.display().to_string());
+        }
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("Plugin check(s) failed: {}", failed.join(", "));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SCHEMA_VERSION;
+
+    fn empty_report() -> Report {
+        Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("example_registry.txt"),
+            0,
+            Vec::new(),
+        )
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_plugins_exit_status() {
+        let report = empty_report();
+
+        assert!(run_plugins(&[PathBuf::from("true")], &report, SCHEMA_VERSION).is_ok());
+        assert!(run_plugins(&[PathBuf::from("cat")], &report, SCHEMA_VERSION).is_ok());
+
+        // A failing plugin fails the run, even after a passing one
+        let err = run_plugins(
+            &[PathBuf::from("true"), PathBuf::from("false")],
+            &report,
+            SCHEMA_VERSION,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("false"));
+    }
+
+    #[test]
+    fn test_run_plugins_missing_executable() {
+        let report = empty_report();
+        let result = run_plugins(
+            &[PathBuf::from("./no-such-plugin")],
+            &report,
+            SCHEMA_VERSION,
+        );
+        assert!(result.is_err());
+    }
+}
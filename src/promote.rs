@@ -0,0 +1,327 @@
+use crate::config::Config;
+use crate::gates;
+use crate::parse_crate_name_version;
+use crate::registry::{self, Registry};
+use crate::trace;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Action recorded for promoted entries
+pub const PROMOTE: &str = "promote";
+
+/// Action recorded for entries added to an environment with `--write --env`
+pub const ADD: &str = "add";
+
+/// A line of the promotion audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// RFC 3339 time in UTC
+    pub time: String,
+    pub action: String,
+    pub crate_file: String,
+    /// Source environment; absent for entries added directly
+    pub from: Option<String>,
+    pub to: String,
+    /// `$USER` of whoever ran the promotion, when set
+    pub user: Option<String>,
+}
+
+/// Entries to promote: exact crate files, or every version of a crate given by name
+pub fn select_entries(registry: &Registry, crates: &[String], all: bool) -> Result<Vec<String>> {
+    let mut selected: Vec<String> = if all {
+        registry.entries.iter().cloned().collect()
+    } else {
+        let mut selected = Vec::new();
+        for wanted in crates {
+            let matching: Vec<String> = registry
+                .entries
+                .iter()
+                .filter(|entry| {
+                    *entry == wanted
+                        || parse_crate_name_version(entry).is_some_and(|(name, _)| name == *wanted)
+                })
+                .cloned()
+                .collect();
+            if matching.is_empty() {
+                anyhow::bail!("{} is not in the source registry", wanted);
+            }
+            selected.extend(matching);
+        }
+        selected
+    };
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+/// Read the audit log; a missing log is empty
+fn read_audit(path: &Path) -> Result<Vec<AuditEntry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Could not read audit log {:?}", path));
+        }
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Could not parse audit log {:?}", path))
+        })
+        .collect()
+}
+
+/// Append entries to the JSON-lines audit log, in a single write
+fn append_audit(path: &Path, entries: &[AuditEntry]) -> Result<()> {
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry)?);
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open audit log {:?}", path))?;
+    file.write_all(lines.as_bytes())
+        .with_context(|| format!("Could not write audit log {:?}", path))
+}
+
+/// Record entries added to an environment's registry, when an audit log is configured
+pub fn record_additions(
+    config: &Config,
+    env: &str,
+    crate_files: &[String],
+    time: DateTime<Utc>,
+) -> Result<()> {
+    let Some(audit_log) = &config.promotion.audit_log else {
+        return Ok(());
+    };
+    let entries: Vec<AuditEntry> = crate_files
+        .iter()
+        .map(|crate_file| AuditEntry {
+            time: time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            action: ADD.to_string(),
+            crate_file: crate_file.clone(),
+            from: None,
+            to: env.to_string(),
+            user: std::env::var("USER").ok(),
+        })
+        .collect();
+    append_audit(audit_log, &entries)
+}
+
+/// Copy registry entries from one environment to the next, recording each in the audit log
+pub fn promote(
+    config: &Config,
+    from: &str,
+    to: Option<&str>,
+    crates: &[String],
+    all: bool,
+    time: DateTime<Utc>,
+) -> Result<()> {
+    let source = config.environment(from)?;
+    let to = match to {
+        Some(to) => to,
+        None => source.promote_to.as_deref().with_context(|| {
+            format!(
+                "Environment {:?} has no promote_to; pass --to explicitly",
+                from
+            )
+        })?,
+    };
+    let target = config.environment(to)?;
+
+    let source_registry = Registry::load(&source.registry_file)?;
+    let mut target_registry = Registry::load(&target.registry_file)?;
+
+    let mut promoted = Vec::new();
+    for crate_file in select_entries(&source_registry, crates, all)? {
+        if target_registry.entries.contains(&crate_file) {
+            eprintln!("{} is already in {}", crate_file, to);
+        } else {
+            promoted.push(crate_file);
+        }
+    }
+
+    if promoted.is_empty() {
+        eprintln!("Nothing to promote.");
+        return Ok(());
+    }
+
+    let history = match &config.promotion.audit_log {
+        Some(audit_log) => read_audit(audit_log)?,
+        None => Vec::new(),
+    };
+    let failures = trace::span("gates", &[("environment", &to)], || {
+        gates::check(
+            &target.gates,
+            from,
+            &source_registry,
+            &promoted,
+            &history,
+            time,
+        )
+    })?;
+    if !failures.is_empty() {
+        eprintln!("Promotion gates for {} failed:", to);
+        for failure in &failures {
+            eprintln!("  - {}", failure);
+        }
+        anyhow::bail!(
+            "Promotion refused: {} gate failure(s), nothing was promoted",
+            failures.len()
+        );
+    }
+
+    let mut audit = Vec::new();
+    for crate_file in promoted {
+        target_registry.insert_from(&source_registry, &crate_file);
+        audit.push(AuditEntry {
+            time: time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            action: PROMOTE.to_string(),
+            crate_file,
+            from: Some(from.to_string()),
+            to: to.to_string(),
+            user: std::env::var("USER").ok(),
+        });
+    }
+
+    // Soak-days gates read the audit log, so a promotion must never go unrecorded:
+    // put the old listing back when the log cannot be written
+    let previous = fs::read(&target.registry_file)
+        .with_context(|| format!("Could not read {:?}", target.registry_file))?;
+    target_registry.save(&target.registry_file)?;
+    if let Some(audit_log) = &config.promotion.audit_log
+        && let Err(err) = append_audit(audit_log, &audit)
+    {
+        registry::write_atomic(&target.registry_file, &previous).with_context(|| {
+            format!(
+                "{:#}; restoring {:?} also failed, it holds unaudited entries",
+                err, target.registry_file
+            )
+        })?;
+        return Err(err.context(format!("Promotion to {} undone", to)));
+    }
+    for entry in &audit {
+        println!("Promoted {} from {} to {}", entry.crate_file, from, to);
+    }
+    eprintln!("Promoted {} entries to {}", audit.len(), to);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::fs;
+
+    #[test]
+    fn test_promote() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(
+            dir.join("dev.txt"),
+            "serde-1.0.228.crate\nserde-1.0.200.crate\nanyhow-1.0.100.crate\n",
+        )
+        .unwrap();
+        fs::write(dir.join("staging.txt"), "serde-1.0.200.crate\n").unwrap();
+        fs::write(dir.join("production.txt"), "serde-1.0.200.crate\n").unwrap();
+
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [environments.dev]
+            registry_file = "{0}/dev.txt"
+            promote_to = "staging"
+
+            [environments.staging]
+            registry_file = "{0}/staging.txt"
+            promote_to = "production"
+
+            [environments.production]
+            registry_file = "{0}/production.txt"
+
+            [environments.production.gates]
+            min_soak_days = 7
+
+            [promotion]
+            audit_log = "{0}/audit.jsonl"
+            "#,
+            dir.display()
+        ))
+        .unwrap();
+        let time = Utc.with_ymd_and_hms(2026, 10, 15, 9, 30, 0).unwrap();
+
+        promote(&config, "dev", None, &["serde".to_string()], false, time).unwrap();
+        // Production requires a week in staging
+        let refused = promote(&config, "staging", None, &[], true, time);
+        assert!(
+            refused
+                .unwrap_err()
+                .to_string()
+                .contains("1 gate failure(s)")
+        );
+        record_additions(&config, "dev", &["anyhow-1.0.100.crate".to_string()], time).unwrap();
+        assert!(promote(&config, "dev", None, &["tokio".to_string()], false, time).is_err());
+        assert!(promote(&config, "production", None, &[], true, time).is_err());
+
+        let staging = fs::read_to_string(dir.join("staging.txt")).unwrap();
+        let production = fs::read_to_string(dir.join("production.txt")).unwrap();
+        let audit = fs::read_to_string(dir.join("audit.jsonl")).unwrap();
+
+        assert_eq!(staging, "serde-1.0.200.crate\nserde-1.0.228.crate\n");
+        assert_eq!(production, "serde-1.0.200.crate\n");
+        // Refused and already-present entries are not audited
+        let entries: Vec<AuditEntry> = audit
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].crate_file, "serde-1.0.228.crate");
+        assert_eq!(entries[1].action, ADD);
+        assert_eq!(entries[1].from, None);
+        assert_eq!(entries[0].time, "2026-10-15T09:30:00Z");
+        assert_eq!(
+            (entries[0].from.as_deref(), entries[0].to.as_str()),
+            (Some("dev"), "staging")
+        );
+    }
+
+    #[test]
+    fn test_promote_without_audit() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::write(dir.join("dev.txt"), "serde-1.0.228.crate\n").unwrap();
+        fs::write(dir.join("staging.txt"), "serde-1.0.200.crate\n").unwrap();
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [environments.dev]
+            registry_file = "{0}/dev.txt"
+            promote_to = "staging"
+
+            [environments.staging]
+            registry_file = "{0}/staging.txt"
+
+            [promotion]
+            audit_log = "{0}/missing/audit.jsonl"
+            "#,
+            dir.display()
+        ))
+        .unwrap();
+        let time = Utc.with_ymd_and_hms(2026, 10, 15, 9, 30, 0).unwrap();
+
+        // An audit log that cannot be written undoes the promotion
+        let err = promote(&config, "dev", None, &[], true, time).unwrap_err();
+        assert!(format!("{:#}", err).contains("Could not open audit log"));
+        assert_eq!(
+            fs::read_to_string(dir.join("staging.txt")).unwrap(),
+            "serde-1.0.200.crate\n"
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::{Context, Result};
+use semver::Version;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Comparison operators of the query language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Substring match on strings
+    Contains,
+}
+
+/// A parsed `--query` filter over missing crates
+///
+/// ```text
+/// expr    := and ("||" and)*
+/// and     := unary ("&&" unary)*
+/// unary   := "!" unary | "(" expr ")" | path op literal
+/// path    := field ("." field)*          e.g. status, declared_in.line
+/// op      := == | != | < | <= | > | >= | ~
+/// literal := "string" | number | true | false | null
+/// ```
+///
+/// Fields are those of a missing crate in the JSON report; absent fields are `null`.
+/// On array fields (e.g. `introduced_by`) a comparison matches if any element does.
+/// `<`/`>` compare versions when both sides are semver versions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Or(Box<Query>, Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Compare(Vec<String>, Op, Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Dot,
+    Open,
+    Close,
+}
+
+/// Consume a two-character operator such as `&&`
+fn pair(chars: &mut Peekable<Chars>, first: char, second: char, token: Token) -> Result<Token> {
+    chars.next();
+    match chars.next() {
+        Some(next) if next == second => Ok(token),
+        _ => anyhow::bail!("Expected '{}{}' in query", first, second),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        let token = match c {
+            ' ' | '\t' | '\n' => {
+                chars.next();
+                continue;
+            }
+            '&' => pair(&mut chars, c, '&', Token::And)?,
+            '|' => pair(&mut chars, c, '|', Token::Or)?,
+            '=' => pair(&mut chars, c, '=', Token::Op(Op::Eq))?,
+            '.' | '(' | ')' | '~' => {
+                chars.next();
+                match c {
+                    '.' => Token::Dot,
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Op(Op::Contains),
+                }
+            }
+            '!' | '<' | '>' => {
+                chars.next();
+                let with_eq = chars.next_if_eq(&'=').is_some();
+                match (c, with_eq) {
+                    ('!', false) => Token::Not,
+                    ('!', true) => Token::Op(Op::Ne),
+                    ('<', false) => Token::Op(Op::Lt),
+                    ('<', true) => Token::Op(Op::Le),
+                    ('>', false) => Token::Op(Op::Gt),
+                    _ => Token::Op(Op::Ge),
+                }
+            }
+            '"' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => string.extend(chars.next()),
+                        Some(c) => string.push(c),
+                        None => anyhow::bail!("Unterminated string in query"),
+                    }
+                }
+                Token::Literal(Value::String(string))
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+                {
+                    number.push(c);
+                }
+                let value: serde_json::Number = number
+                    .parse()
+                    .with_context(|| format!("Invalid number '{}' in query", number))?;
+                Token::Literal(Value::Number(value))
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    _ => Token::Ident(word),
+                }
+            }
+            other => anyhow::bail!("Unexpected '{}' in query", other),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Query> {
+        let mut query = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query> {
+        let mut query = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+        Ok(query)
+    }
+
+    fn unary(&mut self) -> Result<Query> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => anyhow::bail!("Expected ')' in query"),
+                }
+            }
+            Some(Token::Ident(field)) => {
+                let mut path = vec![field];
+                while self.peek() == Some(&Token::Dot) {
+                    self.next();
+                    match self.next() {
+                        Some(Token::Ident(field)) => path.push(field),
+                        _ => anyhow::bail!("Expected a field name after '.' in query"),
+                    }
+                }
+                let Some(Token::Op(op)) = self.next() else {
+                    anyhow::bail!("Expected a comparison after '{}' in query", path.join("."));
+                };
+                let Some(Token::Literal(value)) = self.next() else {
+                    anyhow::bail!("Expected a value after the comparison in query");
+                };
+                Ok(Query::Compare(path, op, value))
+            }
+            _ => anyhow::bail!("Expected a field, '!' or '(' in query"),
+        }
+    }
+}
+
+/// Order two scalar values: versions by semver, then numbers, then strings
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::String(l), Value::String(r)) => match (Version::parse(l), Version::parse(r)) {
+            (Ok(l), Ok(r)) => Some(l.cmp(&r)),
+            _ => Some(l.cmp(r)),
+        },
+        (Value::Number(l), Value::Number(r)) => l.as_f64()?.partial_cmp(&r.as_f64()?),
+        _ => None,
+    }
+}
+
+fn matches_value(field: &Value, op: Op, literal: &Value) -> bool {
+    if let Value::Array(items) = field {
+        return match op {
+            // "not equal" on a list means no element is equal
+            Op::Ne => !items
+                .iter()
+                .any(|item| matches_value(item, Op::Eq, literal)),
+            _ => items.iter().any(|item| matches_value(item, op, literal)),
+        };
+    }
+
+    match op {
+        Op::Eq => field == literal,
+        Op::Ne => field != literal,
+        Op::Contains => match (field, literal) {
+            (Value::String(field), Value::String(literal)) => field.contains(literal.as_str()),
+            _ => false,
+        },
+        Op::Lt => compare_values(field, literal) == Some(Ordering::Less),
+        Op::Le => matches!(
+            compare_values(field, literal),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+        Op::Gt => compare_values(field, literal) == Some(Ordering::Greater),
+        Op::Ge => matches!(
+            compare_values(field, literal),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+    }
+}
+
+impl Query {
+    /// Parse a query expression (also used as the clap value parser)
+    pub fn parse(input: &str) -> Result<Query> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        let query = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            anyhow::bail!("Unexpected trailing input in query");
+        }
+        Ok(query)
+    }
+
+    /// Does the JSON value (a missing crate) match the query
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            Query::Or(left, right) => left.matches(value) || right.matches(value),
+            Query::And(left, right) => left.matches(value) && right.matches(value),
+            Query::Not(query) => !query.matches(value),
+            Query::Compare(path, op, literal) => {
+                let field = path
+                    .iter()
+                    .try_fold(value, |value, field| value.get(field))
+                    .unwrap_or(&Value::Null);
+                matches_value(field, *op, literal)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            Query::parse(r#"status == "new""#).unwrap(),
+            Query::Compare(vec!["status".to_string()], Op::Eq, json!("new"))
+        );
+        assert!(Query::parse(r#"status == "new" && !(license ~ "MIT" || owner == null)"#).is_ok());
+        assert!(Query::parse("declared_in.line >= 10").is_ok());
+
+        assert!(Query::parse("status =").is_err());
+        assert!(Query::parse(r#"status == "new"#).is_err());
+        assert!(Query::parse(r#"(status == "new""#).is_err());
+        assert!(Query::parse(r#"status == "new" license"#).is_err());
+        assert!(Query::parse("status").is_err());
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let missing = json!({
+            "name": "ring",
+            "version": "0.17.8",
+            "status": "new",
+            "license": "ISC AND MIT",
+            "introduced_by": ["rustls", "reqwest"],
+            "declared_in": {"line": 12},
+        });
+        let matches = |query: &str| Query::parse(query).unwrap().matches(&missing);
+
+        assert!(matches(r#"status == "new" && license != "MIT""#));
+        assert!(matches(r#"license ~ "ISC""#));
+        assert!(!matches(
+            r#"status == "version-mismatch" || name == "openssl""#
+        ));
+        assert!(matches(r#"introduced_by == "rustls""#));
+        assert!(!matches(r#"introduced_by != "reqwest""#));
+        assert!(matches(r#"version < "0.17.10""#));
+        assert!(matches("declared_in.line > 9"));
+        assert!(matches("owner == null"));
+        assert!(matches(r#"!(owner != null)"#));
+    }
+}
@@ -0,0 +1,578 @@
+use crate::fingerprint::sha256;
+use crate::parse_crate_name_version;
+use crate::trace;
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+/// On-disk layout of a registry listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryFormat {
+    /// One crate file name per line, e.g. "serde-1.0.228.crate"
+    Text,
+    /// A YAML list of `{name, version, checksum}` entries
+    Yaml,
+}
+
+impl RegistryFormat {
+    /// Listings named `*.yaml` / `*.yml` are YAML, everything else is text
+    pub fn from_path(path: &Path) -> RegistryFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => RegistryFormat::Yaml,
+            _ => RegistryFormat::Text,
+        }
+    }
+}
+
+/// Registry file name standing for stdin when reading and stdout when writing
+pub const STDIO: &str = "-";
+
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO)
+}
+
+/// stdin can only be read once, but both the fingerprint and the check need the listing
+static STDIN: OnceLock<String> = OnceLock::new();
+
+/// Content of a listing file, or of stdin for `-`
+pub fn read_listing(path: &Path) -> Result<String> {
+    if !is_stdio(path) {
+        return fs::read_to_string(path).context("Could not read registry file");
+    }
+    if let Some(content) = STDIN.get() {
+        return Ok(content.clone());
+    }
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .context("Could not read the registry listing from stdin")?;
+    Ok(STDIN.get_or_init(|| content).clone())
+}
+
+/// Start of a registry cache file; bump the number when the layout changes
+const CACHE_MAGIC: &[u8] = b"registry_checker registry cache 2\n";
+
+/// Per-user directory for parse caches: `$XDG_CACHE_HOME/registry_checker`, else
+/// `~/.cache/registry_checker`
+fn cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".cache")))?;
+    Some(base.join("registry_checker"))
+}
+
+/// Cache file of a listing inside the cache directory, named after its absolute path
+fn cache_path(dir: &Path, path: &Path) -> PathBuf {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let key = sha256(path.to_string_lossy().as_bytes());
+    dir.join(format!("{}.bin", key))
+}
+
+/// Size and modification time of a listing; while both are unchanged the cache is used
+/// without reading the listing at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    secs: u64,
+    nanos: u32,
+}
+
+impl Stamp {
+    fn of(metadata: &fs::Metadata) -> Option<Stamp> {
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Stamp {
+            len: metadata.len(),
+            secs: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+/// What a cache was built from: the listing's stamp, and the SHA-256 of its content for
+/// when only the stamp changed (e.g. a fresh checkout)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CacheKey {
+    stamp: Option<Stamp>,
+    sha256: String,
+}
+
+struct CacheReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CacheReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = self.bytes.split_at_checked(len)?;
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+}
+
+/// An entry of a YAML registry listing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YamlEntry {
+    pub name: String,
+    pub version: Version,
+    /// SHA-256 of the .crate file, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// SPDX license expression, checked by promotion gates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Crate that replaces this one upstream, e.g. `clap` for `structopt`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+}
+
+/// The offline registry listing
+#[derive(Debug)]
+pub struct Registry {
+    pub format: RegistryFormat,
+    /// Entries as crate file names; unparseable text lines are kept as-is
+    pub entries: HashSet<String>,
+    /// Checksums from a YAML listing, keyed by crate file name
+    checksums: HashMap<String, String>,
+    /// Licenses from a YAML listing, keyed by crate file name
+    licenses: HashMap<String, String>,
+    /// Replacements for superseded crates from a YAML listing, keyed by crate name
+    superseded: HashMap<String, String>,
+}
+
+impl Registry {
+    /// Load a listing; `-` reads a text listing from stdin
+    ///
+    /// Parsed YAML listings are cached under the user's cache directory. Text listings are
+    /// cheaper to parse than any cache is to decode, so they are always read directly.
+    pub fn load(path: &Path) -> Result<Registry> {
+        trace::span("registry parse", &[("path", &path.display())], || {
+            let format = RegistryFormat::from_path(path);
+            if format == RegistryFormat::Yaml
+                && !is_stdio(path)
+                && let Some(dir) = cache_dir()
+            {
+                return Registry::load_cached(path, &dir);
+            }
+            Registry::parse(&read_listing(path)?, format)
+        })
+    }
+
+    /// Load a YAML listing through the parse cache in `dir`
+    fn load_cached(path: &Path, dir: &Path) -> Result<Registry> {
+        let metadata = fs::metadata(path).context("Could not read registry file")?;
+        let stamp = Stamp::of(&metadata);
+        let cache = cache_path(dir, path);
+        let cached = fs::read(&cache)
+            .ok()
+            .and_then(|bytes| Registry::decode(&bytes));
+        let cached = match cached {
+            Some((key, registry)) if stamp.is_some() && key.stamp == stamp => return Ok(registry),
+            cached => cached,
+        };
+
+        let content = read_listing(path)?;
+        let key = CacheKey {
+            stamp,
+            sha256: sha256(content.as_bytes()),
+        };
+        let registry = match cached {
+            Some((cached_key, registry)) if cached_key.sha256 == key.sha256 => registry,
+            _ => Registry::parse(&content, RegistryFormat::Yaml)?,
+        };
+        // Best effort: without a writable cache directory every run just parses
+        let _ = fs::create_dir_all(dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| write_atomic(&cache, &registry.encode(&key)));
+        Ok(registry)
+    }
+
+    /// An empty listing
+    pub fn new(format: RegistryFormat) -> Registry {
+        Registry {
+            format,
+            entries: HashSet::new(),
+            checksums: HashMap::new(),
+            licenses: HashMap::new(),
+            superseded: HashMap::new(),
+        }
+    }
+
+    fn parse(content: &str, format: RegistryFormat) -> Result<Registry> {
+        let mut registry = Registry::new(format);
+
+        match format {
+            RegistryFormat::Text => {
+                registry.entries = content
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect();
+            }
+            RegistryFormat::Yaml => {
+                let entries: Option<Vec<YamlEntry>> = serde_yaml::from_str(content)
+                    .context("Could not parse YAML registry listing")?;
+                for entry in entries.unwrap_or_default() {
+                    let crate_file = format!("{}-{}.crate", entry.name, entry.version);
+                    if let Some(checksum) = entry.checksum {
+                        registry.checksums.insert(crate_file.clone(), checksum);
+                    }
+                    if let Some(license) = entry.license {
+                        registry.licenses.insert(crate_file.clone(), license);
+                    }
+                    if let Some(replacement) = entry.superseded_by {
+                        registry.superseded.insert(entry.name, replacement);
+                    }
+                    registry.entries.insert(crate_file);
+                }
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Add an entry copied from another listing, keeping its checksum and superseded marker
+    pub fn insert_from(&mut self, other: &Registry, crate_file: &str) {
+        if let Some(checksum) = other.checksums.get(crate_file) {
+            self.checksums
+                .insert(crate_file.to_string(), checksum.clone());
+        }
+        if let Some(license) = other.licenses.get(crate_file) {
+            self.licenses
+                .insert(crate_file.to_string(), license.clone());
+        }
+        if let Some((name, _)) = parse_crate_name_version(crate_file)
+            && let Some(replacement) = other.superseded.get(&name)
+        {
+            self.superseded.insert(name, replacement.clone());
+        }
+        self.entries.insert(crate_file.to_string());
+    }
+
+    /// Checksum recorded for an entry
+    pub fn checksum(&self, crate_file: &str) -> Option<&str> {
+        self.checksums.get(crate_file).map(String::as_str)
+    }
+
+    /// Record an entry's checksum (kept in YAML listings only)
+    pub fn set_checksum(&mut self, crate_file: &str, checksum: String) {
+        self.checksums.insert(crate_file.to_string(), checksum);
+    }
+
+    /// License recorded for an entry
+    pub fn license(&self, crate_file: &str) -> Option<&str> {
+        self.licenses.get(crate_file).map(String::as_str)
+    }
+
+    /// Replacement recorded for a superseded crate
+    pub fn superseded_by(&self, name: &str) -> Option<&str> {
+        self.superseded.get(name).map(String::as_str)
+    }
+
+    /// Binary cache layout: magic, stamp (a presence byte, size, seconds and nanoseconds),
+    /// content hash, then the entries and each metadata map as length-prefixed UTF-8
+    /// strings, sorted
+    fn encode(&self, key: &CacheKey) -> Vec<u8> {
+        fn put(out: &mut Vec<u8>, value: &str) {
+            out.extend((value.len() as u32).to_le_bytes());
+            out.extend(value.as_bytes());
+        }
+        fn put_map(out: &mut Vec<u8>, map: &HashMap<String, String>) {
+            let mut pairs: Vec<_> = map.iter().collect();
+            pairs.sort();
+            out.extend((pairs.len() as u32).to_le_bytes());
+            for (key, value) in pairs {
+                put(out, key);
+                put(out, value);
+            }
+        }
+
+        let mut out = CACHE_MAGIC.to_vec();
+        let stamp = key.stamp.unwrap_or(Stamp {
+            len: 0,
+            secs: 0,
+            nanos: 0,
+        });
+        out.push(u8::from(key.stamp.is_some()));
+        out.extend(stamp.len.to_le_bytes());
+        out.extend(stamp.secs.to_le_bytes());
+        out.extend(stamp.nanos.to_le_bytes());
+        put(&mut out, &key.sha256);
+        let mut entries: Vec<&String> = self.entries.iter().collect();
+        entries.sort();
+        out.extend((entries.len() as u32).to_le_bytes());
+        for entry in entries {
+            put(&mut out, entry);
+        }
+        put_map(&mut out, &self.checksums);
+        put_map(&mut out, &self.licenses);
+        put_map(&mut out, &self.superseded);
+        out
+    }
+
+    /// Read a cache written by `encode` with the key it was built from; `None` when it is
+    /// unreadable
+    fn decode(bytes: &[u8]) -> Option<(CacheKey, Registry)> {
+        let mut reader = CacheReader {
+            bytes: bytes.strip_prefix(CACHE_MAGIC)?,
+        };
+        let has_stamp = reader.take(1)? == [1];
+        let stamp = Stamp {
+            len: reader.u64()?,
+            secs: reader.u64()?,
+            nanos: reader.u32()?,
+        };
+        let key = CacheKey {
+            stamp: has_stamp.then_some(stamp),
+            sha256: reader.string()?,
+        };
+
+        let mut registry = Registry::new(RegistryFormat::Yaml);
+        for _ in 0..reader.u32()? {
+            registry.entries.insert(reader.string()?);
+        }
+        for map in [
+            &mut registry.checksums,
+            &mut registry.licenses,
+            &mut registry.superseded,
+        ] {
+            for _ in 0..reader.u32()? {
+                map.insert(reader.string()?, reader.string()?);
+            }
+        }
+        reader.bytes.is_empty().then_some((key, registry))
+    }
+
+    /// Render the listing, sorted
+    fn render(&self) -> Result<String> {
+        let mut sorted: Vec<&String> = self.entries.iter().collect();
+        sorted.sort();
+
+        match self.format {
+            RegistryFormat::Text => Ok(sorted.iter().map(|line| format!("{}\n", line)).collect()),
+            RegistryFormat::Yaml => {
+                let mut entries: Vec<YamlEntry> = sorted
+                    .iter()
+                    .filter_map(|crate_file| {
+                        let (name, version) = parse_crate_name_version(crate_file)?;
+                        Some(YamlEntry {
+                            checksum: self.checksums.get(*crate_file).cloned(),
+                            license: self.licenses.get(*crate_file).cloned(),
+                            superseded_by: self.superseded.get(&name).cloned(),
+                            name,
+                            version,
+                        })
+                    })
+                    .collect();
+                entries.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+                Ok(serde_yaml::to_string(&entries)?)
+            }
+        }
+    }
+
+    /// Overwrite the listing file with the sorted content; `-` writes it to stdout
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = self.render()?;
+        if is_stdio(path) {
+            let mut stdout = io::stdout().lock();
+            stdout
+                .write_all(content.as_bytes())
+                .and_then(|()| stdout.flush())
+                .context("Failed to write the registry listing to stdout")?;
+            return Ok(());
+        }
+        write_atomic(path, content.as_bytes()).context("Failed to write registry file")
+    }
+}
+
+/// Replace a file's content in one step: the content goes to a temporary file next to it,
+/// which is then renamed over the target, so an interrupted run never leaves it half written
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("{:?} is not a file path", path))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    let written = File::create(&tmp).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        writer.write_all(content)?;
+        writer.into_inner()?.sync_all()
+    });
+    if let Err(err) = written.and_then(|()| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err).with_context(|| format!("Could not write {:?}", path));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_format_from_path() {
+        assert_eq!(
+            RegistryFormat::from_path(Path::new("registry.txt")),
+            RegistryFormat::Text
+        );
+        assert_eq!(
+            RegistryFormat::from_path(Path::new("registry.yaml")),
+            RegistryFormat::Yaml
+        );
+        assert_eq!(
+            RegistryFormat::from_path(Path::new("registry.yml")),
+            RegistryFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn test_yaml_registry_round_trip() {
+        let yaml = "- name: serde\n  version: 1.0.228\n  checksum: 9a8b\n- name: anyhow\n  version: 1.0.100\n";
+        let mut registry = Registry::parse(yaml, RegistryFormat::Yaml).unwrap();
+
+        assert_eq!(registry.len(), 2);
+        assert!(registry.entries.contains("serde-1.0.228.crate"));
+        assert!(registry.entries.contains("anyhow-1.0.100.crate"));
+
+        registry
+            .entries
+            .insert("proc-macro2-1.0.105.crate".to_string());
+        assert_eq!(
+            registry.render().unwrap(),
+            "- name: anyhow\n  version: 1.0.100\n\
+             - name: proc-macro2\n  version: 1.0.105\n\
+             - name: serde\n  version: 1.0.228\n  checksum: 9a8b\n"
+        );
+
+        // An empty file is an empty registry
+        assert_eq!(Registry::parse("", RegistryFormat::Yaml).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_yaml_registry_superseded() {
+        let yaml = "- name: structopt\n  version: 0.3.26\n  superseded_by: clap\n\
+                    - name: clap\n  version: 4.5.54\n";
+        let mut registry = Registry::parse(yaml, RegistryFormat::Yaml).unwrap();
+
+        assert_eq!(registry.superseded_by("structopt"), Some("clap"));
+        assert_eq!(registry.superseded_by("clap"), None);
+
+        // Other versions of a superseded crate carry the marker too
+        registry
+            .entries
+            .insert("structopt-0.3.25.crate".to_string());
+        assert_eq!(
+            registry.render().unwrap(),
+            "- name: clap\n  version: 4.5.54\n\
+             - name: structopt\n  version: 0.3.25\n  superseded_by: clap\n\
+             - name: structopt\n  version: 0.3.26\n  superseded_by: clap\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_from() {
+        let dev = Registry::parse(
+            "- name: serde\n  version: 1.0.228\n  checksum: 9a8b\n  license: MIT OR Apache-2.0\n\
+             - name: structopt\n  version: 0.3.26\n  superseded_by: clap\n",
+            RegistryFormat::Yaml,
+        )
+        .unwrap();
+        let mut staging = Registry::parse("", RegistryFormat::Yaml).unwrap();
+
+        staging.insert_from(&dev, "serde-1.0.228.crate");
+        staging.insert_from(&dev, "structopt-0.3.26.crate");
+        assert_eq!(staging.render().unwrap(), dev.render().unwrap());
+        assert_eq!(
+            staging.license("serde-1.0.228.crate"),
+            Some("MIT OR Apache-2.0")
+        );
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("registry.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        write_atomic(&path, b"new\n").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let leftovers = fs::read_dir(dir).unwrap().count();
+        // A failed write leaves the old content in place
+        let failed = write_atomic(&dir.join("missing/registry.txt"), b"new\n");
+
+        assert_eq!(content, "new\n");
+        assert_eq!(leftovers, 1);
+        assert!(failed.is_err());
+    }
+
+    #[test]
+    fn test_registry_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache_dir = cache_dir.path();
+        let path = dir.path().join("registry.yaml");
+        fs::write(
+            &path,
+            "- name: serde\n  version: 1.0.228\n  checksum: 9a8b\n  license: MIT\n\
+             - name: structopt\n  version: 0.3.26\n  superseded_by: clap\n",
+        )
+        .unwrap();
+
+        let parsed = Registry::load_cached(&path, cache_dir).unwrap();
+        let cache = cache_path(cache_dir, &path);
+        assert!(cache.exists());
+        // Nothing is left next to the listing
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let cached = Registry::load_cached(&path, cache_dir).unwrap();
+        assert_eq!(cached.entries, parsed.entries);
+        assert_eq!(cached.render().unwrap(), parsed.render().unwrap());
+
+        let bytes = fs::read(&cache).unwrap();
+        let (key, _) = Registry::decode(&bytes).unwrap();
+        assert_eq!(key.sha256, sha256(fs::read(&path).unwrap().as_slice()));
+        assert!(Registry::decode(&bytes[..bytes.len() - 1]).is_none());
+
+        // A changed listing is parsed again
+        fs::write(&path, "- name: anyhow\n  version: 1.0.100\n").unwrap();
+        let changed = Registry::load_cached(&path, cache_dir).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(changed.entries.contains("anyhow-1.0.100.crate"));
+    }
+
+    #[test]
+    fn test_text_registry_render() {
+        let mut registry = Registry::parse(
+            "serde-1.0.228.crate\n\n  anyhow-1.0.100.crate\n",
+            RegistryFormat::Text,
+        )
+        .unwrap();
+        registry.entries.insert("clap-4.5.54.crate".to_string());
+
+        assert_eq!(
+            registry.render().unwrap(),
+            "anyhow-1.0.100.crate\nclap-4.5.54.crate\nserde-1.0.228.crate\n"
+        );
+    }
+}
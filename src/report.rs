@@ -0,0 +1,1229 @@
+use crate::fingerprint::Fingerprint;
+use crate::i18n::{Lang, Msg};
+use crate::layout::{Layout, Renderer};
+use crate::manifest::Provenance;
+use crate::owners::UNOWNED;
+use anyhow::Result;
+use schemars::JsonSchema;
+use semver::Version;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Current version of the JSON report layout
+///
+/// Fields may be added within a version; removing, renaming or changing the
+/// meaning of a field bumps the version, and older layouts stay available
+/// through `--schema-version`
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Why a crate resolved by cargo is missing from the offline registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// The crate does not appear in the registry at all
+    New,
+    /// The registry has the crate but no version satisfies the requirement
+    VersionMismatch,
+}
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// Nothing to do, e.g. a crate provided by the toolchain
+    Info,
+    /// The lockfile can move back to a version already in the registry
+    Warning,
+    /// The crate needs approval before the project can build offline
+    Error,
+}
+
+/// A single crate missing from the offline registry
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MissingCrate {
+    pub name: String,
+    pub version: Version,
+    /// Registry file entry for the needed version, e.g. "serde-1.0.228.crate"
+    pub crate_file: String,
+    pub requirement: String,
+    /// The requirement with every bound spelled out, e.g. ">=1.0.0, <2.0.0" for "1.0"
+    pub canonical_requirement: String,
+    /// The requirement as written in the manifest; absent for transitive dependencies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub declared_requirement: Option<String>,
+    pub status: Status,
+    /// Versions of this crate already in the registry
+    pub registry_versions: Vec<Version>,
+    pub reason: String,
+    pub severity: Severity,
+    /// Owning team from the ownership file ("unowned" if no rule matches);
+    /// absent when no ownership file is used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Manifest entry that declared the requirement; absent for transitive dependencies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub declared_in: Option<Provenance>,
+    /// Older registry version the lockfile could use instead (transitive dependencies only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compatible_fallback: Option<Version>,
+    /// Direct dependencies of the project that pull this crate in
+    pub introduced_by: Vec<String>,
+    /// License (SPDX expression) declared by the crate
+    pub license: Option<String>,
+    /// Replacement recorded in the registry when the crate is superseded;
+    /// migrate to it instead of approving this crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+    /// Canonical crates.io download URL (with `--show-urls`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    /// Download URL on the configured mirror (with `--show-urls` and `mirror_url` set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror_url: Option<String>,
+    /// Typosquatting signals (with `--typosquat`), e.g. "name resembles serde (1 edit)"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suspicious: Vec<String>,
+    /// Ignore entry from the config file that matched but has expired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_ignore: Option<IgnoreNote>,
+}
+
+/// Reason and expiry date of a config ignore entry
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct IgnoreNote {
+    pub reason: String,
+    /// Last day the entry applies (YYYY-MM-DD); absent when it never expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+}
+
+/// A missing crate left out of the findings by a config ignore entry
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct IgnoredCrate {
+    pub name: String,
+    pub version: Version,
+    #[serde(flatten)]
+    pub ignore: IgnoreNote,
+    /// Always `info` while the entry is in effect
+    pub severity: Severity,
+}
+
+/// A resolved crate that is provided by the toolchain instead of the registry
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ProvidedCrate {
+    pub name: String,
+    pub version: Version,
+    /// Always `info`: nothing needs to be approved
+    pub severity: Severity,
+}
+
+/// Result of checking a project against the offline registry
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Report {
+    pub schema_version: u32,
+    /// Tool version and input hashes the report was produced from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<Fingerprint>,
+    /// Checked package, or the workspace directory when several members are checked
+    pub project: String,
+    pub manifest_path: PathBuf,
+    pub registry_file: PathBuf,
+    /// Number of entries in the registry file
+    pub registry_size: usize,
+    /// Number of crates.io dependencies resolved by cargo tree
+    pub dependency_count: usize,
+    pub summary: Summary,
+    /// Missing crates, sorted by name
+    pub missing: Vec<MissingCrate>,
+    /// Missing crates no ownership rule matches
+    pub unowned: Vec<String>,
+    /// Resolved crates provided by the toolchain, excluded from the check
+    pub provided: Vec<ProvidedCrate>,
+    /// Missing crates ignored by unexpired config entries
+    pub ignored: Vec<IgnoredCrate>,
+    /// Commands that move the lockfile back to versions already in the registry
+    pub fixits: Vec<String>,
+}
+
+/// Counts of missing crates by status
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Summary {
+    pub missing: usize,
+    pub new: usize,
+    pub version_mismatch: usize,
+    pub needs_approval: usize,
+    /// Findings by severity; `info` counts provided and ignored crates
+    pub errors: usize,
+    pub warnings: usize,
+    pub info: usize,
+}
+
+impl Report {
+    pub fn new(
+        manifest_path: PathBuf,
+        registry_file: PathBuf,
+        dependency_count: usize,
+        missing: Vec<MissingCrate>,
+    ) -> Report {
+        let mut report = Report {
+            schema_version: SCHEMA_VERSION,
+            fingerprint: None,
+            project: String::new(),
+            manifest_path,
+            registry_file,
+            registry_size: 0,
+            dependency_count,
+            summary: Summary::default(),
+            unowned: missing
+                .iter()
+                .filter(|m| m.owner.as_deref() == Some(UNOWNED))
+                .map(|m| m.crate_file.clone())
+                .collect(),
+            fixits: Vec::new(),
+            missing,
+            provided: Vec::new(),
+            ignored: Vec::new(),
+        };
+        report.fixits = report.fixit_commands();
+        report.summary = Summary {
+            missing: report.missing.len(),
+            new: report.count_status(Status::New),
+            version_mismatch: report.count_status(Status::VersionMismatch),
+            needs_approval: report.needs_approval().count(),
+            errors: report.count_severity(Severity::Error),
+            warnings: report.count_severity(Severity::Warning),
+            info: 0,
+        };
+        report
+    }
+
+    /// Copy of the report keeping only the missing crates `keep` accepts,
+    /// with the summary and derived lists recomputed
+    pub fn filtered(&self, keep: impl Fn(&MissingCrate) -> bool) -> Report {
+        let missing = self.missing.iter().filter(|m| keep(m)).cloned().collect();
+        let mut report = Report::new(
+            self.manifest_path.clone(),
+            self.registry_file.clone(),
+            self.dependency_count,
+            missing,
+        );
+        report.fingerprint = self.fingerprint.clone();
+        report.project = self.project.clone();
+        report.registry_size = self.registry_size;
+        report.set_provided(self.provided.clone());
+        report.set_ignored(self.ignored.clone());
+        report
+    }
+
+    /// Record the crates provided by the toolchain as informational findings
+    pub fn set_provided(&mut self, provided: Vec<ProvidedCrate>) {
+        self.provided = provided;
+        self.summary.info = self.provided.len() + self.ignored.len();
+    }
+
+    /// Record the crates ignored by the config as informational findings
+    pub fn set_ignored(&mut self, ignored: Vec<IgnoredCrate>) {
+        self.ignored = ignored;
+        self.summary.info = self.provided.len() + self.ignored.len();
+    }
+
+    /// `cargo update --precise` commands for crates missing only because the
+    /// lockfile is ahead of the registry
+    fn fixit_commands(&self) -> Vec<String> {
+        let manifest_arg = if self.manifest_path == Path::new("./Cargo.toml") {
+            String::new()
+        } else {
+            format!(" --manifest-path {}", self.manifest_path.display())
+        };
+
+        self.missing
+            .iter()
+            .filter_map(|m| {
+                let fallback = m.compatible_fallback.as_ref()?;
+                Some(format!(
+                    "cargo update{} -p {}@{} --precise {}",
+                    manifest_arg, m.name, m.version, fallback
+                ))
+            })
+            .collect()
+    }
+
+    fn count_status(&self, status: Status) -> usize {
+        self.missing.iter().filter(|m| m.status == status).count()
+    }
+
+    fn count_severity(&self, severity: Severity) -> usize {
+        self.missing
+            .iter()
+            .filter(|m| m.severity == severity)
+            .count()
+    }
+
+    /// Number of findings that should fail the run
+    pub fn blocking(&self, warnings_as_errors: bool) -> usize {
+        if warnings_as_errors {
+            self.summary.errors + self.summary.warnings
+        } else {
+            self.summary.errors
+        }
+    }
+
+    /// Crates that need approval before they can be added to the registry
+    pub fn needs_approval(&self) -> impl Iterator<Item = &MissingCrate> {
+        // Every missing crate currently needs approval
+        self.missing.iter()
+    }
+}
+
+/// Frozen layout of schema version 1 (no `schema_version` or `summary` fields)
+mod v1 {
+    use super::{MissingCrate, Report, Status};
+    use schemars::JsonSchema;
+    use semver::Version;
+    use serde::Serialize;
+    use std::path::PathBuf;
+
+    #[derive(Serialize, JsonSchema)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum StatusV1 {
+        New,
+        VersionMismatch,
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    pub struct MissingCrateV1 {
+        pub name: String,
+        pub version: Version,
+        pub crate_file: String,
+        pub requirement: String,
+        pub status: StatusV1,
+        pub registry_versions: Vec<Version>,
+        pub reason: String,
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    pub struct ReportV1 {
+        pub manifest_path: PathBuf,
+        pub registry_file: PathBuf,
+        pub dependency_count: usize,
+        pub missing: Vec<MissingCrateV1>,
+    }
+
+    /// Reason worded as it was in version 1, with the requirement as cargo prints it rather
+    /// than in canonical form
+    fn reason(missing: &MissingCrate) -> String {
+        match missing.status {
+            Status::New => missing.reason.clone(),
+            Status::VersionMismatch => {
+                let versions: Vec<String> = missing
+                    .registry_versions
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect();
+                format!(
+                    "requirement \"{}\" not satisfied by registry versions [{}]",
+                    missing.requirement,
+                    versions.join(", ")
+                )
+            }
+        }
+    }
+
+    impl From<&Report> for ReportV1 {
+        fn from(report: &Report) -> ReportV1 {
+            ReportV1 {
+                manifest_path: report.manifest_path.clone(),
+                registry_file: report.registry_file.clone(),
+                dependency_count: report.dependency_count,
+                missing: report
+                    .missing
+                    .iter()
+                    .map(|m| MissingCrateV1 {
+                        name: m.name.clone(),
+                        version: m.version.clone(),
+                        crate_file: m.crate_file.clone(),
+                        requirement: m.requirement.clone(),
+                        status: match m.status {
+                            Status::New => StatusV1::New,
+                            Status::VersionMismatch => StatusV1::VersionMismatch,
+                        },
+                        registry_versions: m.registry_versions.clone(),
+                        reason: reason(m),
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+/// Serialize the report using the layout of the given schema version
+pub fn to_json(report: &Report, schema_version: u32) -> Result<serde_json::Value> {
+    Ok(match schema_version {
+        1 => serde_json::to_value(v1::ReportV1::from(report))?,
+        SCHEMA_VERSION => serde_json::to_value(report)?,
+        other => anyhow::bail!("Unsupported schema version: {}", other),
+    })
+}
+
+/// JSON Schema describing the report layout of the given schema version
+pub fn json_schema(schema_version: u32) -> Result<serde_json::Value> {
+    let schema = match schema_version {
+        1 => schemars::schema_for!(v1::ReportV1),
+        SCHEMA_VERSION => schemars::schema_for!(Report),
+        other => anyhow::bail!("Unsupported schema version: {}", other),
+    };
+    Ok(serde_json::to_value(schema)?)
+}
+
+/// Fail when the report has blocking findings
+pub fn check_blocking(report: &Report, warnings_as_errors: bool) -> Result<()> {
+    let blocking = report.blocking(warnings_as_errors);
+    if blocking > 0 {
+        anyhow::bail!(
+            "{} blocking finding(s): {} error(s), {} warning(s){}",
+            blocking,
+            report.summary.errors,
+            report.summary.warnings,
+            if warnings_as_errors {
+                " (warnings treated as errors)"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Status line printed when the check itself fails
+pub const ERROR_STATUS_LINE: &str = "RESULT status=error";
+
+/// Single-line summary with a stable format, for grep-based pipelines
+///
+/// `missing` counts all missing crates, `new` those not in the registry at all and
+/// `approved` those whose crate is already approved but needs another version; `status`
+/// fails exactly when `check_blocking` does
+pub fn status_line(report: &Report, warnings_as_errors: bool) -> String {
+    let status = if report.blocking(warnings_as_errors) == 0 {
+        "pass"
+    } else {
+        "fail"
+    };
+    format!(
+        "RESULT missing={} new={} approved={} status={}",
+        report.summary.missing, report.summary.new, report.summary.version_mismatch, status
+    )
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render the missing crates as CSV, one row per crate
+pub fn render_csv(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    // Comment lines, skipped by CSV readers configured with `#` comments
+    for line in report.fingerprint.iter().flat_map(Fingerprint::lines) {
+        writeln!(out, "# {}", line.trim_start())?;
+    }
+    writeln!(
+        out,
+        "crate,version,requirement,status,reason,introduced_by,license"
+    )?;
+
+    for missing in &report.missing {
+        let status = match missing.status {
+            Status::New => "new",
+            Status::VersionMismatch => "version-mismatch",
+        };
+        let fields = [
+            missing.name.as_str(),
+            &missing.version.to_string(),
+            &missing.requirement,
+            status,
+            &missing.reason,
+            &missing.introduced_by.join("; "),
+            missing.license.as_deref().unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    Ok(())
+}
+
+/// Which missing crates the human-readable output lists
+///
+/// Counts and the other sections always cover the whole report
+#[derive(Debug, Clone, Copy, Default)]
+pub struct View {
+    /// Show at most this many crates per list
+    pub limit: Option<usize>,
+    /// Show only crates with this status
+    pub only: Option<Status>,
+}
+
+impl View {
+    fn select<'a>(&self, crates: impl Iterator<Item = &'a MissingCrate>) -> Vec<&'a MissingCrate> {
+        crates
+            .filter(|m| self.only.is_none_or(|status| m.status == status))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Note how many crates a filtered list leaves out
+fn write_hidden<W: Write>(r: &mut Renderer<W>, shown: usize, total: usize) -> io::Result<()> {
+    if shown < total {
+        let text = r
+            .lang()
+            .format(Msg::MoreNotShown, &[("count", &(total - shown))]);
+        r.line(format!("  {}", text))?;
+    }
+    Ok(())
+}
+
+fn registry_versions_text(missing: &MissingCrate) -> String {
+    let versions: Vec<String> = missing
+        .registry_versions
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    versions.join(", ")
+}
+
+/// Why the crate is missing, in the report language
+fn reason(lang: Lang, missing: &MissingCrate) -> String {
+    if lang == Lang::En {
+        return missing.reason.clone();
+    }
+    match missing.status {
+        Status::New => lang.text(Msg::ReasonNew).to_string(),
+        Status::VersionMismatch => lang.format(
+            Msg::ReasonMismatch,
+            &[
+                ("requirement", &missing.canonical_requirement),
+                ("versions", &registry_versions_text(missing)),
+            ],
+        ),
+    }
+}
+
+/// Where the requirement comes from: the manifest entry, or the direct dependencies
+fn origin(lang: Lang, missing: &MissingCrate) -> Option<String> {
+    if let Some(declared_in) = &missing.declared_in {
+        Some(match &missing.declared_requirement {
+            Some(raw) => lang.format(
+                Msg::DeclaredInAs,
+                &[("location", declared_in), ("requirement", raw)],
+            ),
+            None => lang.format(Msg::DeclaredIn, &[("location", declared_in)]),
+        })
+    } else if !missing.introduced_by.is_empty() {
+        Some(lang.format(
+            Msg::IntroducedBy,
+            &[("crates", &missing.introduced_by.join(", "))],
+        ))
+    } else {
+        None
+    }
+}
+
+fn expired_ignore(lang: Lang, missing: &MissingCrate) -> Option<String> {
+    match &missing.expired_ignore {
+        Some(IgnoreNote {
+            reason,
+            until: Some(until),
+        }) => Some(lang.format(Msg::IgnoreExpired, &[("until", until), ("reason", reason)])),
+        _ => None,
+    }
+}
+
+/// Everything known about a missing crate, as one line (`--compact`)
+fn compact_line(lang: Lang, missing: &MissingCrate) -> String {
+    let mut parts = vec![format!(
+        "  {}: {}",
+        missing.crate_file,
+        reason(lang, missing)
+    )];
+    if let Some(owner) = &missing.owner {
+        parts.push(lang.format(Msg::Owner, &[("owner", owner)]));
+    }
+    if let Some(replacement) = &missing.superseded_by {
+        parts.push(lang.format(Msg::Superseded, &[("replacement", replacement)]));
+    }
+    if !missing.suspicious.is_empty() {
+        parts.push(lang.format(
+            Msg::Typosquat,
+            &[("signals", &missing.suspicious.join(", "))],
+        ));
+    }
+    parts.extend(expired_ignore(lang, missing));
+    parts.extend(origin(lang, missing));
+    if let Some(url) = &missing.download_url {
+        parts.push(format!("crates.io: {}", url));
+    }
+    if let Some(url) = &missing.mirror_url {
+        parts.push(lang.format(Msg::Mirror, &[("url", url)]));
+    }
+    parts.join("; ")
+}
+
+/// A missing crate with its details on the lines below
+fn write_detailed<W: Write>(r: &mut Renderer<W>, missing: &MissingCrate) -> io::Result<()> {
+    let lang = r.lang();
+    let owner = missing
+        .owner
+        .as_ref()
+        .map(|owner| format!(" ({})", lang.format(Msg::Owner, &[("owner", owner)])))
+        .unwrap_or_default();
+    let tag = match missing.status {
+        Status::VersionMismatch => lang.format(
+            Msg::MismatchTag,
+            &[
+                ("requirement", &missing.canonical_requirement),
+                ("versions", &registry_versions_text(missing)),
+            ],
+        ),
+        Status::New => lang.text(Msg::NewTag).to_string(),
+    };
+    r.line(format!("  {} {}{}", missing.crate_file, tag, owner))?;
+    if let Some(url) = &missing.download_url {
+        r.line(format!("      crates.io: {}", url))?;
+    }
+    if let Some(url) = &missing.mirror_url {
+        r.line(format!(
+            "      {}",
+            lang.format(Msg::Mirror, &[("url", url)])
+        ))?;
+    }
+    if let Some(expired) = expired_ignore(lang, missing) {
+        r.line(format!("      {}", expired))?;
+    }
+    if let Some(origin) = origin(lang, missing) {
+        r.line(format!("      {}", origin))?;
+    }
+    Ok(())
+}
+
+/// Render the report as the human-readable console output
+pub fn render_human(
+    report: &Report,
+    view: &View,
+    layout: Layout,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let r = &mut Renderer::new(out, layout);
+    let lang = r.lang();
+
+    if let Some(fingerprint) = &report.fingerprint {
+        for line in fingerprint.lines() {
+            r.verbatim(line)?;
+        }
+        r.blank()?;
+    }
+
+    if !report.provided.is_empty() {
+        r.line(lang.text(Msg::ProvidedHeader))?;
+        for provided in &report.provided {
+            r.line(format!("  {} v{}", provided.name, provided.version))?;
+        }
+        r.blank()?;
+    }
+
+    if !report.ignored.is_empty() {
+        r.line(lang.text(Msg::IgnoredHeader))?;
+        for ignored in &report.ignored {
+            let note = match &ignored.ignore.until {
+                Some(until) => lang.format(
+                    Msg::IgnoredUntil,
+                    &[("reason", &ignored.ignore.reason), ("until", until)],
+                ),
+                None => ignored.ignore.reason.clone(),
+            };
+            r.line(format!(
+                "  {} v{} ({})",
+                ignored.name, ignored.version, note
+            ))?;
+        }
+        r.blank()?;
+    }
+
+    if report.missing.is_empty() {
+        r.line(lang.text(if report.ignored.is_empty() {
+            Msg::AllPresent
+        } else {
+            Msg::AllOtherPresent
+        }))?;
+        return Ok(());
+    }
+
+    r.line(lang.format(Msg::FoundMissing, &[("count", &report.missing.len())]))?;
+
+    let listed = view.select(report.missing.iter());
+    for missing in &listed {
+        if r.compact() {
+            r.line(compact_line(lang, missing))?;
+        } else {
+            write_detailed(r, missing)?;
+        }
+    }
+    write_hidden(r, listed.len(), report.missing.len())?;
+
+    let needs_approval: Vec<&MissingCrate> = report.needs_approval().collect();
+
+    // The compact list already carries everything the approval sections repeat
+    if !needs_approval.is_empty() && !r.compact() {
+        // Summary of what needs approval
+        r.blank()?;
+        r.line(format!(
+            " {}",
+            lang.format(Msg::RequireApproval, &[("count", &needs_approval.len())])
+        ))?;
+        r.line(format!("   {}", lang.text(Msg::NoCompatible)))?;
+
+        // Shown ahead of the approval list so reviewers cannot miss them
+        let suspicious: Vec<&&MissingCrate> = needs_approval
+            .iter()
+            .filter(|m| !m.suspicious.is_empty())
+            .collect();
+        if !suspicious.is_empty() {
+            r.blank()?;
+            r.line(lang.text(Msg::TyposquatHeader))?;
+            for missing in suspicious {
+                r.line(format!(
+                    "  - {}: {}",
+                    missing.crate_file,
+                    missing.suspicious.join("; ")
+                ))?;
+            }
+        }
+
+        // Detailed list of crates requiring approval
+        r.blank()?;
+        r.rule()?;
+        r.line(lang.text(Msg::ApprovalHeader))?;
+        r.rule()?;
+        let listed = view.select(needs_approval.iter().copied());
+        for missing in &listed {
+            let reason = reason(lang, missing);
+            match &missing.superseded_by {
+                Some(replacement) => r.line(format!(
+                    "  - {} ({}; {})",
+                    missing.crate_file,
+                    reason,
+                    lang.format(Msg::Superseded, &[("replacement", replacement)])
+                ))?,
+                None => r.line(format!("  - {} ({})", missing.crate_file, reason))?,
+            }
+        }
+        write_hidden(r, listed.len(), needs_approval.len())?;
+        r.rule()?;
+    }
+
+    r.blank()?;
+    r.line(lang.format(
+        Msg::Severity,
+        &[
+            ("errors", &report.summary.errors),
+            ("warnings", &report.summary.warnings),
+            ("info", &report.summary.info),
+        ],
+    ))?;
+
+    if !report.fixits.is_empty() {
+        r.blank()?;
+        r.line(lang.text(Msg::FixitHeader))?;
+        r.line(lang.text(Msg::FixitRun))?;
+        for command in &report.fixits {
+            // Kept whole so they can be copied
+            r.verbatim(format!("  {}", command))?;
+        }
+    }
+
+    if !report.unowned.is_empty() {
+        r.blank()?;
+        r.line(lang.text(Msg::UnownedHeader))?;
+        for crate_file in &report.unowned {
+            r.line(format!("  - {}", crate_file))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// One new crate, serde 1.0.228, declared by crates/api
+    pub(crate) fn sample_report() -> Report {
+        Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            2,
+            vec![MissingCrate {
+                name: "serde".to_string(),
+                version: Version::parse("1.0.228").unwrap(),
+                crate_file: "serde-1.0.228.crate".to_string(),
+                requirement: "^1.0".to_string(),
+                canonical_requirement: ">=1.0.0, <2.0.0".to_string(),
+                declared_requirement: Some("1.0".to_string()),
+                status: Status::New,
+                registry_versions: Vec::new(),
+                reason: "new dependency".to_string(),
+                severity: Severity::Error,
+                owner: Some(UNOWNED.to_string()),
+                declared_in: Some(Provenance {
+                    manifest: PathBuf::from("crates/api/Cargo.toml"),
+                    key_path: "dependencies.serde".to_string(),
+                    line: 7,
+                }),
+                compatible_fallback: None,
+                introduced_by: vec!["serde_json".to_string()],
+                license: Some("MIT OR Apache-2.0".to_string()),
+                superseded_by: None,
+                download_url: None,
+                mirror_url: None,
+                suspicious: Vec::new(),
+                expired_ignore: None,
+            }],
+        )
+    }
+
+    #[test]
+    fn test_to_json_schema_versions() {
+        let report = sample_report();
+
+        let v2 = to_json(&report, 2).unwrap();
+        assert_eq!(v2["schema_version"], 2);
+        assert_eq!(v2["summary"]["new"], 1);
+        assert_eq!(v2["missing"][0]["status"], "new");
+        assert_eq!(v2["missing"][0]["owner"], "unowned");
+        assert_eq!(v2["unowned"][0], "serde-1.0.228.crate");
+        assert_eq!(
+            v2["missing"][0]["declared_in"]["key_path"],
+            "dependencies.serde"
+        );
+        assert_eq!(v2["missing"][0]["declared_in"]["line"], 7);
+
+        // Version 1 keeps the original layout
+        let v1 = to_json(&report, 1).unwrap();
+        assert!(v1.get("schema_version").is_none());
+        assert!(v1.get("summary").is_none());
+        assert!(v1["missing"][0].get("owner").is_none());
+        assert_eq!(v1["missing"][0]["crate_file"], "serde-1.0.228.crate");
+
+        // Reasons keep their version 1 wording; version 2 spells the requirement out
+        let mut mismatch = report;
+        mismatch.missing[0].status = Status::VersionMismatch;
+        mismatch.missing[0].registry_versions = vec![Version::new(0, 9, 0)];
+        mismatch.missing[0].reason =
+            "requirement \">=1.0.0, <2.0.0\" not satisfied by registry versions [0.9.0]"
+                .to_string();
+        assert_eq!(
+            to_json(&mismatch, 1).unwrap()["missing"][0]["reason"],
+            "requirement \"^1.0\" not satisfied by registry versions [0.9.0]"
+        );
+        assert_eq!(
+            to_json(&mismatch, 2).unwrap()["missing"][0]["reason"],
+            "requirement \">=1.0.0, <2.0.0\" not satisfied by registry versions [0.9.0]"
+        );
+
+        assert!(to_json(&mismatch, 3).is_err());
+    }
+
+    #[test]
+    fn test_fixit_commands() {
+        let mut report = sample_report();
+        assert!(report.fixits.is_empty());
+
+        let mut missing = report.missing[0].clone();
+        missing.status = Status::VersionMismatch;
+        missing.compatible_fallback = Some(Version::parse("1.0.200").unwrap());
+        missing.severity = Severity::Warning;
+        report = Report::new(
+            PathBuf::from("crates/api/Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            1,
+            vec![missing],
+        );
+
+        assert_eq!(
+            report.fixits,
+            vec![
+                "cargo update --manifest-path crates/api/Cargo.toml -p serde@1.0.228 --precise 1.0.200"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_severity_blocking() {
+        let mut report = sample_report();
+        let mut warning = report.missing[0].clone();
+        warning.name = "tokio".to_string();
+        warning.severity = Severity::Warning;
+        report = Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            2,
+            vec![report.missing.remove(0), warning.clone()],
+        );
+        report.set_provided(vec![ProvidedCrate {
+            name: "std".to_string(),
+            version: Version::parse("1.0.0").unwrap(),
+            severity: Severity::Info,
+        }]);
+
+        assert_eq!(report.summary.errors, 1);
+        assert_eq!(report.summary.warnings, 1);
+        assert_eq!(report.summary.info, 1);
+        assert_eq!(report.blocking(false), 1);
+        assert_eq!(report.blocking(true), 2);
+        assert!(check_blocking(&report, false).is_err());
+
+        let advisory = Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            1,
+            vec![warning],
+        );
+        assert!(check_blocking(&advisory, false).is_ok());
+        assert!(check_blocking(&advisory, true).is_err());
+        assert_eq!(
+            to_json(&advisory, SCHEMA_VERSION).unwrap()["missing"][0]["severity"],
+            "warning"
+        );
+    }
+
+    #[test]
+    fn test_filtered() {
+        let mut report = sample_report();
+        report.project = "api".to_string();
+
+        let kept = report.filtered(|m| m.name == "serde");
+        assert_eq!(kept.missing.len(), 1);
+        assert_eq!(kept.project, "api");
+
+        let none = report.filtered(|m| m.status == Status::VersionMismatch);
+        assert!(none.missing.is_empty());
+        assert_eq!(none.summary.missing, 0);
+        assert!(none.unowned.is_empty());
+    }
+
+    #[test]
+    fn test_status_line() {
+        assert_eq!(
+            status_line(&sample_report(), false),
+            "RESULT missing=1 new=1 approved=0 status=fail"
+        );
+
+        // Warnings only fail the line when they fail the run
+        let mut warning = sample_report().missing.remove(0);
+        warning.severity = Severity::Warning;
+        let warnings = Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            1,
+            vec![warning],
+        );
+        assert!(check_blocking(&warnings, false).is_ok());
+        assert_eq!(
+            status_line(&warnings, false),
+            "RESULT missing=1 new=1 approved=0 status=pass"
+        );
+        assert!(check_blocking(&warnings, true).is_err());
+        assert_eq!(
+            status_line(&warnings, true),
+            "RESULT missing=1 new=1 approved=0 status=fail"
+        );
+
+        let report = Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            0,
+            Vec::new(),
+        );
+        assert_eq!(
+            status_line(&report, false),
+            "RESULT missing=0 new=0 approved=0 status=pass"
+        );
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let mut report = sample_report();
+        report.missing[0].reason =
+            "requirement \"^1.0\" not satisfied, registry has 0.9".to_string();
+
+        let mut csv = Vec::new();
+        render_csv(&report, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "crate,version,requirement,status,reason,introduced_by,license\n\
+             serde,1.0.228,^1.0,new,\"requirement \"\"^1.0\"\" not satisfied, registry has 0.9\",serde_json,MIT OR Apache-2.0\n"
+        );
+    }
+
+    #[test]
+    fn test_render_human_view() {
+        let mut report = sample_report();
+        let mut mismatch = report.missing[0].clone();
+        mismatch.name = "tokio".to_string();
+        mismatch.crate_file = "tokio-1.40.0.crate".to_string();
+        mismatch.status = Status::VersionMismatch;
+        report = Report::new(
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("registry.txt"),
+            2,
+            vec![report.missing.remove(0), mismatch],
+        );
+
+        let render = |view: View| {
+            let mut out = Vec::new();
+            render_human(&report, &view, Layout::default(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let full = render(View::default());
+        assert!(full.contains("serde-1.0.228.crate [WARNING"));
+        assert!(full.contains("tokio-1.40.0.crate"));
+        assert!(!full.contains("more not shown"));
+
+        let only = render(View {
+            limit: None,
+            only: Some(Status::VersionMismatch),
+        });
+        assert!(only.contains("Found 2 dependencies"));
+        assert!(!only.contains("  - serde-1.0.228.crate (new dependency)"));
+        assert!(only.contains("  - tokio-1.40.0.crate (new dependency)"));
+        assert!(only.contains("... 1 more not shown"));
+
+        let limited = render(View {
+            limit: Some(1),
+            only: None,
+        });
+        assert!(limited.contains("  serde-1.0.228.crate [WARNING"));
+        assert!(!limited.contains("  tokio-1.40.0.crate ["));
+    }
+
+    #[test]
+    fn test_render_human_ignored() {
+        let mut report = sample_report();
+        report.missing[0].expired_ignore = Some(IgnoreNote {
+            reason: "ticket-123".to_string(),
+            until: Some("2025-12-31".to_string()),
+        });
+        report.set_ignored(vec![IgnoredCrate {
+            name: "windows-sys".to_string(),
+            version: Version::parse("0.59.0").unwrap(),
+            ignore: IgnoreNote {
+                reason: "Windows support lands in Q1".to_string(),
+                until: Some("2026-03-01".to_string()),
+            },
+            severity: Severity::Info,
+        }]);
+
+        let mut out = Vec::new();
+        render_human(&report, &View::default(), Layout::default(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(
+            "Ignored by config:\n  windows-sys v0.59.0 (Windows support lands in Q1; until 2026-03-01)\n"
+        ));
+        assert!(out.contains(
+            "  serde-1.0.228.crate [WARNING: NEW dependency, requires approval] (owner: unowned)\n\
+             \x20     ignore expired on 2025-12-31 (ticket-123)\n"
+        ));
+        assert!(out.contains("1 error(s), 0 warning(s), 1 info"));
+
+        let json = to_json(&report, 2).unwrap();
+        assert_eq!(json["ignored"][0]["reason"], "Windows support lands in Q1");
+        assert_eq!(json["missing"][0]["expired_ignore"]["until"], "2025-12-31");
+    }
+
+    #[test]
+    fn test_render_human_superseded() {
+        let mut report = sample_report();
+        report.missing[0].name = "structopt".to_string();
+        report.missing[0].crate_file = "structopt-0.3.26.crate".to_string();
+        report.missing[0].superseded_by = Some("clap".to_string());
+
+        let mut out = Vec::new();
+        render_human(&report, &View::default(), Layout::default(), &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains(
+            "  - structopt-0.3.26.crate (new dependency; superseded by clap, migrate instead of approving)"
+        ));
+        assert_eq!(
+            to_json(&report, 2).unwrap()["missing"][0]["superseded_by"],
+            "clap"
+        );
+    }
+
+    #[test]
+    fn test_render_human_requirements() {
+        let mut report = sample_report();
+        report.missing[0].status = Status::VersionMismatch;
+        report.missing[0].registry_versions = vec![Version::parse("0.9.0").unwrap()];
+
+        let mut out = Vec::new();
+        render_human(&report, &View::default(), Layout::default(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(
+            "  serde-1.0.228.crate [requirement: \">=1.0.0, <2.0.0\", registry has: 0.9.0; no compatible version]"
+        ));
+        assert!(out.contains(
+            "      declared in crates/api/Cargo.toml:7 [dependencies.serde] as \"1.0\"\n"
+        ));
+
+        let json = to_json(&report, 2).unwrap();
+        assert_eq!(json["missing"][0]["requirement"], "^1.0");
+        assert_eq!(
+            json["missing"][0]["canonical_requirement"],
+            ">=1.0.0, <2.0.0"
+        );
+        assert_eq!(json["missing"][0]["declared_requirement"], "1.0");
+    }
+
+    #[test]
+    fn test_render_human_urls() {
+        let mut report = sample_report();
+        report.missing[0].download_url =
+            Some("https://static.crates.io/crates/serde/serde-1.0.228.crate".to_string());
+        report.missing[0].mirror_url =
+            Some("https://mirror.example.com/crates/serde/1.0.228/download".to_string());
+
+        let mut out = Vec::new();
+        render_human(&report, &View::default(), Layout::default(), &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains(
+            "  serde-1.0.228.crate [WARNING: NEW dependency, requires approval] (owner: unowned)\n\
+             \x20     crates.io: https://static.crates.io/crates/serde/serde-1.0.228.crate\n\
+             \x20     mirror: https://mirror.example.com/crates/serde/1.0.228/download\n"
+        ));
+        assert!(
+            to_json(&sample_report(), 2).unwrap()["missing"][0]
+                .get("download_url")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_render_human_layout() {
+        let mut report = sample_report();
+        report.missing[0].declared_in = None;
+        report.missing[0].introduced_by = vec!["axum".to_string(), "tower".to_string()];
+        let render = |layout: Layout| {
+            let mut out = Vec::new();
+            render_human(&report, &View::default(), layout, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let compact = render(Layout {
+            compact: true,
+            ..Layout::default()
+        });
+        assert!(compact.contains(
+            "  serde-1.0.228.crate: new dependency; owner: unowned; introduced by axum, tower\n"
+        ));
+        assert!(!compact.contains("CRATES REQUIRING APPROVAL"));
+
+        let narrow = render(Layout {
+            width: Some(30),
+            ..Layout::default()
+        });
+        assert!(narrow.lines().all(|line| line.chars().count() <= 30));
+        assert!(
+            narrow.contains(
+                "  serde-1.0.228.crate\n      [WARNING: NEW\n      dependency, requires\n"
+            )
+        );
+        assert!(narrow.contains("\n==============================\n"));
+    }
+
+    #[test]
+    fn test_render_human_lang() {
+        let mut report = sample_report();
+        report.missing[0].status = Status::VersionMismatch;
+        report.missing[0].registry_versions = vec![Version::new(1, 0, 100)];
+        let mut out = Vec::new();
+        let layout = Layout {
+            lang: Lang::De,
+            ..Layout::default()
+        };
+        render_human(&report, &View::default(), layout, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("1 Abhängigkeiten fehlen in der Offline-Registry:\n"));
+        assert!(out.contains(
+            "  - serde-1.0.228.crate (Anforderung \">=1.0.0, <2.0.0\" von den Registry-Versionen [1.0.100] nicht erfüllt)\n"
+        ));
+        assert!(!out.contains("CRATES REQUIRING APPROVAL"));
+    }
+
+    #[test]
+    fn test_render_human_suspicious() {
+        let mut report = sample_report();
+        report.missing[0].suspicious = vec![
+            "name resembles serde_json (2 edits)".to_string(),
+            "only 42 downloads".to_string(),
+        ];
+
+        let mut out = Vec::new();
+        render_human(&report, &View::default(), Layout::default(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(
+            "!!! POSSIBLE TYPOSQUATTING, verify these crates before approving:\n\
+             \x20 - serde-1.0.228.crate: name resembles serde_json (2 edits); only 42 downloads\n"
+        ));
+        assert!(out.find("POSSIBLE TYPOSQUATTING") < out.find("CRATES REQUIRING APPROVAL"));
+        assert_eq!(
+            to_json(&report, 2).unwrap()["missing"][0]["suspicious"][1],
+            "only 42 downloads"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_header() {
+        let mut report = sample_report();
+        report.fingerprint = Some(Fingerprint {
+            tool_version: "0.1.0".to_string(),
+            registry_sha256: "ab12".to_string(),
+            lockfile_sha256: Some("cd34".to_string()),
+            arguments: vec!["-r".to_string(), "registry.txt".to_string()],
+            generated_at: "2026-10-15T08:30:00Z".to_string(),
+        });
+
+        let mut out = Vec::new();
+        render_human(&report, &View::default(), Layout::default(), &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with(
+            "Generated by registry_checker 0.1.0\n  at 2026-10-15T08:30:00Z\n  \
+             arguments: -r registry.txt\n  registry sha256: ab12\n  lockfile sha256: cd34\n\n"
+        ));
+
+        let mut out = Vec::new();
+        render_csv(&report, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert!(
+            csv.starts_with("# Generated by registry_checker 0.1.0\n# at 2026-10-15T08:30:00Z\n")
+        );
+        assert!(csv.contains("# lockfile sha256: cd34\ncrate,version,"));
+
+        // Filtering keeps the fingerprint, JSON and YAML carry it as a field
+        let json = to_json(&report.filtered(|_| false), 2).unwrap();
+        assert_eq!(json["fingerprint"]["registry_sha256"], "ab12");
+        assert_eq!(json["fingerprint"]["arguments"][1], "registry.txt");
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = json_schema(SCHEMA_VERSION).unwrap();
+        assert!(schema["properties"]["schema_version"].is_object());
+        assert!(schema["properties"]["summary"].is_object());
+
+        let schema = json_schema(1).unwrap();
+        assert!(schema["properties"]["schema_version"].is_null());
+        assert!(schema["properties"]["missing"].is_object());
+    }
+}
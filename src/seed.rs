@@ -0,0 +1,229 @@
+use crate::fleet::ProjectErrors;
+use crate::lockfile;
+use crate::owners::glob_match;
+use crate::registry::{Registry, RegistryFormat};
+use crate::trace;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Match path segments against pattern segments, where `**` spans any number of directories
+fn path_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| path_match(rest, &path[skip..])),
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| glob_match(segment, name) && path_match(rest, path)),
+    }
+}
+
+/// Collect files below `dir` whose relative path matches, skipping hidden and `target` directories
+fn walk(
+    dir: &Path,
+    relative: &mut Vec<String>,
+    pattern: &[&str],
+    found: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Could not read directory {:?}", dir))?
+        .collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = entry.file_type()?;
+        relative.push(name.clone());
+        if file_type.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                walk(&entry.path(), relative, pattern, found)?;
+            }
+        } else if file_type.is_file() {
+            let segments: Vec<&str> = relative.iter().map(String::as_str).collect();
+            if path_match(pattern, &segments) {
+                found.push(entry.path());
+            }
+        }
+        relative.pop();
+    }
+    Ok(())
+}
+
+/// Files matching a glob such as `repos/**/Cargo.lock` (`*`, `?` and `**`)
+pub fn glob_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let segments: Vec<&str> = pattern.split('/').collect();
+    let literal = segments
+        .iter()
+        .take_while(|segment| !segment.contains(['*', '?']))
+        .count();
+    if literal == segments.len() {
+        let path = PathBuf::from(pattern);
+        return Ok(if path.is_file() {
+            vec![path]
+        } else {
+            Vec::new()
+        });
+    }
+
+    let base = match segments[..literal].join("/") {
+        base if base.is_empty() && literal == 0 => PathBuf::from("."),
+        base if base.is_empty() => PathBuf::from("/"),
+        base => PathBuf::from(base),
+    };
+    let mut found = Vec::new();
+    walk(&base, &mut Vec::new(), &segments[literal..], &mut found)?;
+    Ok(found)
+}
+
+/// Registry listing of every registry crate the readable lockfiles resolve
+fn seed_registry(
+    lockfiles: &[PathBuf],
+    format: RegistryFormat,
+    errors: &mut ProjectErrors,
+) -> Result<Registry> {
+    let mut registry = Registry::new(format);
+    for lockfile in lockfiles {
+        let packages = trace::span("lockfile parse", &[("path", &lockfile.display())], || {
+            lockfile::packages(lockfile)
+        });
+        let Some(packages) = errors.check(lockfile, packages)? else {
+            continue;
+        };
+        for package in packages {
+            if !package.is_from_registry() {
+                continue;
+            }
+            let crate_file = format!("{}-{}.crate", package.name, package.version);
+            if let Some(checksum) = package.checksum {
+                match registry.checksum(&crate_file) {
+                    Some(seen) if seen != checksum => eprintln!(
+                        "Warning: {} has a different checksum in {:?}; keeping {}",
+                        crate_file, lockfile, seen
+                    ),
+                    Some(_) => {}
+                    None => registry.set_checksum(&crate_file, checksum),
+                }
+            }
+            registry.entries.insert(crate_file);
+        }
+    }
+    Ok(registry)
+}
+
+/// Create a registry listing from everything the matching lockfiles resolve
+///
+/// Unreadable lockfiles are skipped and listed at the end unless `fail_fast` is set.
+pub fn seed(patterns: &[String], out: &Path, fail_fast: bool) -> Result<()> {
+    if out.exists() {
+        anyhow::bail!(
+            "{:?} already exists; seed only creates new listings (use --write or merge-file to extend one)",
+            out
+        );
+    }
+
+    let mut lockfiles = Vec::new();
+    for pattern in patterns {
+        lockfiles.extend(glob_files(pattern)?);
+    }
+    lockfiles.sort();
+    lockfiles.dedup();
+    if lockfiles.is_empty() {
+        anyhow::bail!("No lockfiles match {}", patterns.join(", "));
+    }
+
+    let mut errors = ProjectErrors::new(fail_fast);
+    let registry = seed_registry(&lockfiles, RegistryFormat::from_path(out), &mut errors)?;
+    registry.save(out)?;
+    eprintln!(
+        "Seeded {} with {} crates from {} lockfile(s)",
+        out.display(),
+        registry.len(),
+        lockfiles.len()
+    );
+    errors.render(&mut std::io::stderr())?;
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_match() {
+        let matches = |pattern: &str, path: &str| {
+            let pattern: Vec<&str> = pattern.split('/').collect();
+            let path: Vec<&str> = path.split('/').collect();
+            path_match(&pattern, &path)
+        };
+        assert!(matches("**/Cargo.lock", "Cargo.lock"));
+        assert!(matches("**/Cargo.lock", "team/app/Cargo.lock"));
+        assert!(!matches("**/Cargo.lock", "team/app/Cargo.toml"));
+        assert!(matches("*/Cargo.lock", "app/Cargo.lock"));
+        assert!(!matches("*/Cargo.lock", "team/app/Cargo.lock"));
+        assert!(matches("team-*/**/Cargo.lock", "team-a/x/y/Cargo.lock"));
+        assert!(!matches("team-*/**/Cargo.lock", "other/x/Cargo.lock"));
+    }
+
+    #[test]
+    fn test_seed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for member in ["app", "tools/cli", "app/target/debug", ".git"] {
+            fs::create_dir_all(dir.join(member)).unwrap();
+        }
+        let lock = |packages: &str| format!("version = 4\n\n{}", packages);
+        fs::write(
+            dir.join("app/Cargo.lock"),
+            lock(
+                "[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+                 [[package]]\nname = \"serde\"\nversion = \"1.0.228\"\n\
+                 source = \"registry+https://github.com/rust-lang/crates.io-index\"\nchecksum = \"9a8b\"\n",
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tools/cli/Cargo.lock"),
+            lock(
+                "[[package]]\nname = \"serde\"\nversion = \"1.0.228\"\n\
+                 source = \"registry+https://github.com/rust-lang/crates.io-index\"\nchecksum = \"9a8b\"\n\n\
+                 [[package]]\nname = \"anyhow\"\nversion = \"1.0.100\"\n\
+                 source = \"sparse+https://index.crates.io/\"\n\n\
+                 [[package]]\nname = \"forked\"\nversion = \"0.1.0\"\n\
+                 source = \"git+https://example.com/forked#abc\"\n",
+            ),
+        )
+        .unwrap();
+        // Build output and VCS metadata are not searched
+        fs::write(dir.join("app/target/debug/Cargo.lock"), lock("")).unwrap();
+        fs::write(dir.join(".git/Cargo.lock"), lock("")).unwrap();
+
+        let patterns = [format!("{}/**/Cargo.lock", dir.display())];
+        assert_eq!(
+            glob_files(&patterns[0]).unwrap(),
+            vec![dir.join("app/Cargo.lock"), dir.join("tools/cli/Cargo.lock")]
+        );
+
+        let out = dir.join("registry.yaml");
+        seed(&patterns, &out, false).unwrap();
+        // An existing listing is never overwritten
+        assert!(seed(&patterns, &out, false).is_err());
+        let yaml = fs::read_to_string(&out).unwrap();
+
+        // A broken lockfile is skipped, the others still seed the listing
+        fs::create_dir_all(dir.join("broken")).unwrap();
+        fs::write(dir.join("broken/Cargo.lock"), "[[package]\n").unwrap();
+        let partial = dir.join("partial.txt");
+        assert!(seed(&patterns, &partial, false).is_err());
+        let text = fs::read_to_string(&partial).unwrap();
+        assert!(seed(&patterns, &dir.join("fail-fast.txt"), true).is_err());
+        let fail_fast_written = dir.join("fail-fast.txt").exists();
+
+        assert_eq!(text, "anyhow-1.0.100.crate\nserde-1.0.228.crate\n");
+        assert!(!fail_fast_written);
+
+        assert_eq!(
+            yaml,
+            "- name: anyhow\n  version: 1.0.100\n- name: serde\n  version: 1.0.228\n  checksum: 9a8b\n"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::cancel;
+use crate::config::Config;
+use anyhow::{Context, Result};
+use semver::Version;
+
+/// Extract the version from a distribution response
+/// Accepts a bare version ("0.3.1", "v0.3.1") or a JSON object with a "version" field
+fn parse_distributed_version(body: &str) -> Result<Version> {
+    let body = body.trim();
+
+    let version_str = if body.starts_with('{') {
+        let json: serde_json::Value =
+            serde_json::from_str(body).context("Distribution response is not valid JSON")?;
+        json.get("version")
+            .and_then(|v| v.as_str())
+            .context("Distribution response has no \"version\" field")?
+            .to_string()
+    } else {
+        body.to_string()
+    };
+
+    Version::parse(version_str.trim_start_matches('v'))
+        .with_context(|| format!("Invalid distributed version: {:?}", version_str))
+}
+
+/// Compare the running binary against the configured distribution and version pin
+pub fn check_version(config: &Config, url: Option<&str>, strict: bool) -> Result<()> {
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    println!("Running registry_checker {}", current);
+
+    let distribution = config.distribution.as_ref();
+
+    if let Some(required) = distribution.and_then(|d| d.required_version.as_ref()) {
+        if !required.matches(&current) {
+            anyhow::bail!(
+                "registry_checker {} does not satisfy the required version \"{}\"",
+                current,
+                required
+            );
+        }
+        println!("Version satisfies required version \"{}\"", required);
+    }
+
+    let Some(url) = url.or(distribution.and_then(|d| d.url.as_deref())) else {
+        anyhow::bail!("No distribution URL given (use --url or [distribution] url in the config)");
+    };
+
+    let body = cancel::fetch("version check", {
+        let url = url.to_string();
+        move |agent| {
+            agent
+                .get(&url)
+                .call()
+                .with_context(|| format!("Failed to fetch latest version from {}", url))?
+                .into_string()
+                .context("Failed to read distribution response")
+        }
+    })?;
+    let latest = parse_distributed_version(&body)?;
+
+    if current < latest {
+        let message = format!(
+            "registry_checker {} is out of date; {} is available from {}",
+            current, latest, url
+        );
+        if strict {
+            anyhow::bail!(message);
+        }
+        println!("WARNING: {}", message);
+    } else {
+        println!("Up to date (latest distributed version: {})", latest);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_distributed_version() {
+        let expected = Version::parse("0.3.1").unwrap();
+        assert_eq!(parse_distributed_version("0.3.1\n").unwrap(), expected);
+        assert_eq!(parse_distributed_version("v0.3.1").unwrap(), expected);
+        assert_eq!(
+            parse_distributed_version(r#"{"version": "0.3.1", "url": "x"}"#).unwrap(),
+            expected
+        );
+
+        assert!(parse_distributed_version("<html>not found</html>").is_err());
+        assert!(parse_distributed_version(r#"{"name": "registry_checker"}"#).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::cancel;
+use crate::config::TicketConfig;
+use crate::report::{self, Report, SCHEMA_VERSION};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Replace `${VAR}` references with values from the environment
+/// Unset variables expand to an empty string
+fn expand_env(template: &str) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        match rest[start + 2..].find('}') {
+            Some(end) => {
+                let var = &rest[start + 2..start + 2 + end];
+                output.push_str(&env::var(var).unwrap_or_default());
+                rest = &rest[start + 2 + end + 1..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Replace `{{name}}` placeholders with their values
+/// `{{name|json}}` inserts the value as a quoted JSON string, for use inside JSON bodies
+fn render_template(template: &str, values: &HashMap<&str, String>) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .with_context(|| format!("Unterminated placeholder in ticket template: {}", rest))?;
+        let placeholder = rest[start + 2..start + end].trim();
+
+        let (name, filter) = match placeholder.split_once('|') {
+            Some((name, filter)) => (name.trim(), Some(filter.trim())),
+            None => (placeholder, None),
+        };
+        let value = values
+            .get(name)
+            .with_context(|| format!("Unknown placeholder in ticket template: {}", name))?;
+
+        match filter {
+            None => output.push_str(value),
+            Some("json") => output.push_str(&serde_json::to_string(value)?),
+            Some(other) => anyhow::bail!("Unknown template filter: {}", other),
+        }
+
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Size of a .crate file cargo downloaded while resolving, from its local registry cache
+fn cached_crate_size(crate_file: &str) -> Option<u64> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".cargo")))?;
+    // One directory per registry, e.g. index.crates.io-1949cf8c6b5b557f
+    fs::read_dir(cargo_home.join("registry").join("cache"))
+        .ok()?
+        .filter_map(|entry| fs::metadata(entry.ok()?.path().join(crate_file)).ok())
+        .map(|metadata| metadata.len())
+        .next()
+}
+
+/// Sizes of the crates requiring approval that cargo has downloaded, by crate file
+fn crate_sizes(report: &Report) -> HashMap<String, u64> {
+    report
+        .needs_approval()
+        .filter_map(|missing| {
+            let size = cached_crate_size(&missing.crate_file)?;
+            Some((missing.crate_file.clone(), size))
+        })
+        .collect()
+}
+
+/// Byte count for people, e.g. "76.5 KiB"
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Plain-text list of crates requiring approval, used as the ticket description
+fn approval_text(report: &Report, sizes: &HashMap<String, u64>) -> String {
+    let mut text = format!(
+        "The following crates required by {} are missing from the offline registry and require approval:\n",
+        report.manifest_path.display()
+    );
+    for missing in report.needs_approval() {
+        let mut details = vec![
+            missing.reason.clone(),
+            format!(
+                "license: {}",
+                missing.license.as_deref().unwrap_or("unknown")
+            ),
+        ];
+        details.extend(sizes.get(&missing.crate_file).copied().map(human_size));
+        text.push_str(&format!(
+            "- {} ({})\n",
+            missing.crate_file,
+            details.join("; ")
+        ));
+    }
+    text
+}
+
+/// Values available to the ticket template
+fn template_values(
+    report: &Report,
+    sizes: &HashMap<String, u64>,
+) -> Result<HashMap<&'static str, String>> {
+    let count = report.needs_approval().count();
+    let crates: Vec<&str> = report
+        .needs_approval()
+        .map(|missing| missing.crate_file.as_str())
+        .collect();
+    let licenses: Vec<String> = report
+        .needs_approval()
+        .map(|missing| {
+            format!(
+                "{}: {}",
+                missing.crate_file,
+                missing.license.as_deref().unwrap_or("unknown")
+            )
+        })
+        .collect();
+
+    Ok(HashMap::from([
+        (
+            "summary",
+            format!(
+                "Offline registry approval: {} crate(s) for {}",
+                count,
+                report.manifest_path.display()
+            ),
+        ),
+        ("count", count.to_string()),
+        ("manifest_path", report.manifest_path.display().to_string()),
+        ("crates", crates.join(", ")),
+        ("licenses", licenses.join(", ")),
+        // Only crates cargo has downloaded are counted
+        ("total_size", human_size(sizes.values().sum())),
+        ("report", approval_text(report, sizes)),
+        (
+            "report_json",
+            serde_json::to_string(&report::to_json(report, SCHEMA_VERSION)?)
+                .context("Failed to serialize report")?,
+        ),
+    ]))
+}
+
+/// File a ticket for the crates requiring approval, if there are any
+pub fn create_ticket(ticket: &TicketConfig, report: &Report) -> Result<()> {
+    if report.needs_approval().next().is_none() {
+        eprintln!("No crates require approval; no ticket created.");
+        return Ok(());
+    }
+
+    let values = template_values(report, &crate_sizes(report))?;
+    let url = expand_env(&ticket.url);
+    let body = render_template(&expand_env(&ticket.body), &values)?;
+
+    let method = ticket.method.clone();
+    let headers: Vec<(String, String)> = ticket
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), expand_env(value)))
+        .collect();
+
+    eprintln!("Creating approval ticket at {}...", url);
+    cancel::fetch("ticket creation", move |agent| {
+        let mut request = agent.request(&method, &url);
+        for (name, value) in &headers {
+            request = request.set(name, value);
+        }
+        match request.send_string(&body) {
+            Ok(response) => {
+                eprintln!("Ticket created (HTTP {})", response.status());
+                let response_body = response.into_string().unwrap_or_default();
+                if !response_body.is_empty() {
+                    eprintln!("{}", response_body);
+                }
+                Ok(())
+            }
+            Err(ureq::Error::Status(code, response)) => {
+                let response_body = response.into_string().unwrap_or_default();
+                anyhow::bail!("Ticket creation failed (HTTP {}): {}", code, response_body)
+            }
+            Err(e) => Err(e).context("Ticket creation request failed"),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let values = HashMap::from([
+            ("count", "2".to_string()),
+            ("report", "line one\n\"quoted\"".to_string()),
+        ]);
+
+        assert_eq!(
+            render_template("{{count}} crate(s)", &values).unwrap(),
+            "2 crate(s)"
+        );
+        assert_eq!(
+            render_template(r#"{"description": {{ report|json }}}"#, &values).unwrap(),
+            r#"{"description": "line one\n\"quoted\""}"#
+        );
+
+        assert!(render_template("{{unknown}}", &values).is_err());
+        assert!(render_template("{{count|upper}}", &values).is_err());
+        assert!(render_template("{{count", &values).is_err());
+    }
+
+    #[test]
+    fn test_template_values() {
+        let mut report = crate::report::tests::sample_report();
+        let mut unlicensed = report.missing[0].clone();
+        unlicensed.crate_file = "tokio-1.40.0.crate".to_string();
+        unlicensed.license = None;
+        report.missing.push(unlicensed);
+        let sizes = HashMap::from([("serde-1.0.228.crate".to_string(), 78_336)]);
+
+        let values = template_values(&report, &sizes).unwrap();
+        assert_eq!(
+            values["licenses"],
+            "serde-1.0.228.crate: MIT OR Apache-2.0, tokio-1.40.0.crate: unknown"
+        );
+        assert_eq!(values["total_size"], "76.5 KiB");
+        assert!(values["report"].ends_with(
+            "- serde-1.0.228.crate (new dependency; license: MIT OR Apache-2.0; 76.5 KiB)\n\
+             - tokio-1.40.0.crate (new dependency; license: unknown)\n"
+        ));
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(3 * 1_048_576), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_expand_env() {
+        assert_eq!(expand_env("no variables"), "no variables");
+        assert_eq!(
+            expand_env("Bearer ${REGISTRY_CHECKER_SURELY_UNSET_VAR}"),
+            "Bearer "
+        );
+        assert_eq!(expand_env("unterminated ${VAR"), "unterminated ${VAR");
+    }
+}
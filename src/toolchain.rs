@@ -0,0 +1,62 @@
+use crate::owners::glob_match;
+
+/// Crates shipped with the Rust toolchain (sysroot), which show up in
+/// dependency trees when building std from source but never come from a registry
+pub const BUILTIN_PROVIDED: &[&str] = &[
+    "alloc",
+    "compiler_builtins",
+    "core",
+    "panic_abort",
+    "panic_unwind",
+    "proc_macro",
+    "rustc-std-workspace-alloc",
+    "rustc-std-workspace-core",
+    "rustc-std-workspace-std",
+    "std",
+    "std_detect",
+    "test",
+    "unwind",
+];
+
+/// Crates that are provided by the toolchain or patched internally and must
+/// never be required from the registry
+///
+/// Unlike ignoring a crate, provided crates are listed in the report as
+/// "provided by toolchain"
+#[derive(Debug)]
+pub struct Provided {
+    patterns: Vec<String>,
+}
+
+impl Provided {
+    /// The built-in set extended with user patterns (`*` and `?` wildcards allowed)
+    pub fn new(extra: impl IntoIterator<Item = String>) -> Provided {
+        let mut patterns: Vec<String> = BUILTIN_PROVIDED.iter().map(|s| s.to_string()).collect();
+        patterns.extend(extra);
+        Provided { patterns }
+    }
+
+    pub fn contains(&self, crate_name: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, crate_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provided() {
+        let provided = Provided::new(["internal-*".to_string(), "ring".to_string()]);
+
+        assert!(provided.contains("core"));
+        assert!(provided.contains("rustc-std-workspace-core"));
+        assert!(provided.contains("internal-logging"));
+        assert!(provided.contains("ring"));
+
+        assert!(!provided.contains("serde"));
+        assert!(!provided.contains("ring-compat"));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::cancel;
+use crate::fingerprint::sha256;
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Set by `--otlp-endpoint`; spans are not recorded otherwise
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Spans that have ended, waiting for `export`
+static FINISHED: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+/// Next span ID; 0 is not a valid ID
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// First span opened, the parent of spans that start on worker threads
+static ROOT: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Spans open on this thread, innermost last
+    static OPEN: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// OTLP span status code for a failed operation
+const STATUS_ERROR: u8 = 2;
+
+/// A span that has ended
+#[derive(Debug, Clone)]
+struct Record {
+    id: u64,
+    parent: Option<u64>,
+    name: String,
+    start: u128,
+    end: u128,
+    attributes: Vec<(String, String)>,
+    error: Option<String>,
+}
+
+/// Record spans from now on
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default()
+}
+
+/// Run an operation in a span named `name`, recording how long it took and whether it failed
+///
+/// Spans nest on the thread that opens them; spans of worker threads hang off the first one.
+pub fn span<T>(
+    name: &str,
+    attributes: &[(&str, &dyn Display)],
+    run: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return run();
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    // The first span becomes the root; other spans without a parent on this thread join it
+    let parent = OPEN.with(|open| open.borrow().last().copied()).or_else(|| {
+        ROOT.compare_exchange(0, id, Ordering::SeqCst, Ordering::SeqCst)
+            .err()
+    });
+    let start = now();
+    OPEN.with(|open| open.borrow_mut().push(id));
+    let result = run();
+    OPEN.with(|open| open.borrow_mut().pop());
+
+    let record = Record {
+        id,
+        parent,
+        name: name.to_string(),
+        start,
+        end: now(),
+        attributes: attributes
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        error: result.as_ref().err().map(|error| format!("{:#}", error)),
+    };
+    if let Ok(mut finished) = FINISHED.lock() {
+        finished.push(record);
+    }
+    result
+}
+
+/// 32 hex digits, unique per run
+fn trace_id() -> String {
+    let seed = format!("{} {}", std::process::id(), now());
+    sha256(seed.as_bytes())[..32].to_string()
+}
+
+fn span_id(id: u64) -> String {
+    format!("{:016x}", id)
+}
+
+/// OTLP/HTTP JSON request body carrying the spans as one trace
+fn request_body(trace_id: &str, records: &[Record]) -> Value {
+    let spans: Vec<Value> = records
+        .iter()
+        .map(|record| {
+            let mut span = json!({
+                "traceId": trace_id,
+                "spanId": span_id(record.id),
+                "name": record.name,
+                "kind": 1,
+                "startTimeUnixNano": record.start.to_string(),
+                "endTimeUnixNano": record.end.to_string(),
+                "attributes": record
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| json!({"key": key, "value": {"stringValue": value}}))
+                    .collect::<Vec<_>>(),
+            });
+            if let Some(parent) = record.parent {
+                span["parentSpanId"] = json!(span_id(parent));
+            }
+            if let Some(error) = &record.error {
+                span["status"] = json!({"code": STATUS_ERROR, "message": error});
+            }
+            span
+        })
+        .collect();
+
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    {"key": "service.name", "value": {"stringValue": "registry_checker"}},
+                    {"key": "service.version", "value": {"stringValue": env!("CARGO_PKG_VERSION")}}
+                ]
+            },
+            "scopeSpans": [{
+                "scope": {"name": "registry_checker", "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans
+            }]
+        }]
+    })
+}
+
+/// Traces URL of an OTLP/HTTP collector, given its base URL or the full path
+fn traces_url(endpoint: &str) -> String {
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+    }
+}
+
+/// Send the recorded spans to an OTLP/HTTP collector
+pub fn export(endpoint: &str) -> Result<()> {
+    let records = match FINISHED.lock() {
+        Ok(mut finished) => std::mem::take(&mut *finished),
+        Err(_) => return Ok(()),
+    };
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let url = traces_url(endpoint);
+    let body = request_body(&trace_id(), &records).to_string();
+    cancel::fetch("trace export", move |agent| {
+        agent
+            .post(&url)
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .with_context(|| format!("Failed to send traces to {}", url))?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span() {
+        enable();
+        let result = span("trace test outer", &[("path", &"registry.txt")], || {
+            span("trace test inner", &[], || -> Result<()> {
+                anyhow::bail!("broken")
+            })
+            .ok();
+            Ok(42)
+        });
+        assert_eq!(result.unwrap(), 42);
+
+        let finished = FINISHED.lock().unwrap().clone();
+        let find = |name: &str| finished.iter().find(|r| r.name == name).unwrap();
+        let (outer, inner) = (find("trace test outer"), find("trace test inner"));
+        assert_eq!(inner.parent, Some(outer.id));
+        assert_eq!(inner.error.as_deref(), Some("broken"));
+        assert!(outer.error.is_none());
+        assert!(outer.start <= inner.start && inner.end <= outer.end);
+        assert_eq!(
+            outer.attributes,
+            vec![("path".to_string(), "registry.txt".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_request_body() {
+        let records = [
+            Record {
+                id: 1,
+                parent: None,
+                name: "registry_checker".to_string(),
+                start: 1_000,
+                end: 9_000,
+                attributes: vec![("arguments".to_string(), "-r reg.txt".to_string())],
+                error: None,
+            },
+            Record {
+                id: 2,
+                parent: Some(1),
+                name: "cargo tree".to_string(),
+                start: 2_000,
+                end: 5_000,
+                attributes: Vec::new(),
+                error: Some("cargo tree failed".to_string()),
+            },
+        ];
+        let body = request_body("0af7651916cd43dd8448eb211c80319c", &records);
+        let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+
+        assert_eq!(spans[0]["spanId"], "0000000000000001");
+        assert!(spans[0].get("parentSpanId").is_none());
+        assert_eq!(
+            spans[0]["attributes"][0]["value"]["stringValue"],
+            "-r reg.txt"
+        );
+        assert_eq!(spans[1]["traceId"], "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(spans[1]["parentSpanId"], "0000000000000001");
+        assert_eq!(spans[1]["startTimeUnixNano"], "2000");
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_eq!(spans[1]["status"]["message"], "cargo tree failed");
+    }
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(
+            traces_url("http://localhost:4318"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("http://localhost:4318/"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("https://otel.example.com/v1/traces"),
+            "https://otel.example.com/v1/traces"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::archive::{LATEST, RUN_DIR_FORMAT};
+use crate::report::csv_field;
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Fields of an archived report needed for trends
+/// Everything optional so reports from older versions still load
+#[derive(Debug, Deserialize)]
+struct ArchivedReport {
+    project: Option<String>,
+    manifest_path: PathBuf,
+    summary: Option<ArchivedSummary>,
+    #[serde(default)]
+    missing: Vec<ArchivedMissing>,
+    registry_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchivedMissing {
+    name: Option<String>,
+    requirement: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchivedSummary {
+    missing: usize,
+}
+
+/// One archived run of a project
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub time: NaiveDateTime,
+    pub missing: usize,
+    pub registry_size: Option<usize>,
+    /// Missing crates of the run as (name, requirement)
+    pub demands: Vec<(String, String)>,
+}
+
+/// Time of a run from its directory name, ignoring the same-second suffix
+fn run_time(dir_name: &str) -> Option<NaiveDateTime> {
+    let timestamp = dir_name.split('-').next()?;
+    NaiveDateTime::parse_from_str(timestamp, RUN_DIR_FORMAT).ok()
+}
+
+/// Load every archived run, grouped by project and sorted by time
+pub fn load_history(report_dir: &Path) -> Result<BTreeMap<String, Vec<Point>>> {
+    let mut history: BTreeMap<String, Vec<Point>> = BTreeMap::new();
+
+    let entries = fs::read_dir(report_dir)
+        .with_context(|| format!("Could not read report directory {:?}", report_dir))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == LATEST {
+            continue;
+        }
+        let Some(time) = run_time(&name) else {
+            continue;
+        };
+
+        let report_path = entry.path().join("report.json");
+        let Ok(content) = fs::read_to_string(&report_path) else {
+            continue;
+        };
+        let report: ArchivedReport = serde_json::from_str(&content)
+            .with_context(|| format!("Could not parse {:?}", report_path))?;
+
+        let project = report
+            .project
+            .unwrap_or_else(|| report.manifest_path.display().to_string());
+        let missing = report
+            .summary
+            .map(|summary| summary.missing)
+            .unwrap_or(report.missing.len());
+        let demands = report
+            .missing
+            .into_iter()
+            .filter_map(|m| Some((m.name?, m.requirement?)))
+            .collect();
+
+        history.entry(project).or_default().push(Point {
+            time,
+            missing,
+            registry_size: report.registry_size,
+            demands,
+        });
+    }
+
+    for points in history.values_mut() {
+        points.sort_by_key(|point| point.time);
+    }
+
+    Ok(history)
+}
+
+/// Text sparkline of the values, scaled between their minimum and maximum
+pub fn sparkline(values: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+
+    values
+        .iter()
+        .map(|&value| {
+            if max == min {
+                BARS[0]
+            } else {
+                BARS[(value - min) * (BARS.len() - 1) / (max - min)]
+            }
+        })
+        .collect()
+}
+
+fn render_text(history: &BTreeMap<String, Vec<Point>>, out: &mut impl Write) -> io::Result<()> {
+    if history.is_empty() {
+        writeln!(out, "No archived reports found.")?;
+        return Ok(());
+    }
+
+    for (project, points) in history {
+        let (first, last) = (&points[0], &points[points.len() - 1]);
+        writeln!(
+            out,
+            "{} ({} runs, {} to {})",
+            project,
+            points.len(),
+            first.time.format("%Y-%m-%d"),
+            last.time.format("%Y-%m-%d")
+        )?;
+
+        let missing: Vec<usize> = points.iter().map(|p| p.missing).collect();
+        writeln!(
+            out,
+            "  missing crates  {}  {} -> {}",
+            sparkline(&missing),
+            first.missing,
+            last.missing
+        )?;
+
+        let sizes: Vec<usize> = points.iter().filter_map(|p| p.registry_size).collect();
+        if let (Some(first_size), Some(last_size)) = (sizes.first(), sizes.last()) {
+            writeln!(
+                out,
+                "  registry size   {}  {} -> {}",
+                sparkline(&sizes),
+                first_size,
+                last_size
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn render_csv(history: &BTreeMap<String, Vec<Point>>, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "timestamp,project,missing,registry_size")?;
+    for (project, points) in history {
+        for point in points {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                point.time.format("%Y-%m-%dT%H:%M:%SZ"),
+                csv_field(project),
+                point.missing,
+                point
+                    .registry_size
+                    .map(|s| s.to_string())
+                    .unwrap_or_default()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print missing-crate and registry-size trends from archived reports
+pub fn print_trend(report_dir: &Path, csv: bool) -> Result<()> {
+    let history = load_history(report_dir)?;
+    let mut out = io::stdout().lock();
+    if csv {
+        render_csv(&history, &mut out)?;
+    } else {
+        render_text(&history, &mut out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[12, 9, 5, 5, 0]), "█▆▃▃▁");
+        assert_eq!(sparkline(&[3, 3]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_load_history() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let runs = [
+            (
+                "20261014T090000Z",
+                r#"{"project": "api", "manifest_path": "Cargo.toml", "summary": {"missing": 3}, "registry_size": 120}"#,
+            ),
+            (
+                "20261001T090000Z",
+                r#"{"project": "api", "manifest_path": "Cargo.toml", "summary": {"missing": 7}, "registry_size": 100}"#,
+            ),
+            // Old layout without project, summary or registry size
+            (
+                "20261001T090000Z-1",
+                r#"{"manifest_path": "cli, legacy/Cargo.toml", "missing": [{}, {}]}"#,
+            ),
+        ];
+        for (name, json) in runs {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(dir.join(name).join("report.json"), json).unwrap();
+        }
+
+        let history = load_history(dir).unwrap();
+
+        let api = &history["api"];
+        assert_eq!(api.len(), 2);
+        assert_eq!((api[0].missing, api[0].registry_size), (7, Some(100)));
+        assert_eq!((api[1].missing, api[1].registry_size), (3, Some(120)));
+
+        let cli = &history["cli, legacy/Cargo.toml"];
+        assert_eq!((cli[0].missing, cli[0].registry_size), (2, None));
+
+        let mut csv = Vec::new();
+        render_csv(&history, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "timestamp,project,missing,registry_size\n\
+             2026-10-01T09:00:00Z,api,7,100\n\
+             2026-10-14T09:00:00Z,api,3,120\n\
+             2026-10-01T09:00:00Z,\"cli, legacy/Cargo.toml\",2,\n"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::cancel;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// crates.io API endpoint describing a crate; `{name}` is substituted
+pub const CRATES_IO_API: &str = "https://crates.io/api/v1/crates/{name}";
+
+/// Crates first published this recently are flagged
+const RECENT_DAYS: i64 = 30;
+
+/// Crates downloaded fewer times than this are flagged
+const LOW_DOWNLOADS: u64 = 1_000;
+
+/// Publication data of a crate from the crates.io API
+#[derive(Debug, Clone, PartialEq)]
+pub struct Published {
+    pub created_at: DateTime<Utc>,
+    pub downloads: u64,
+}
+
+#[derive(Deserialize)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    krate: CrateData,
+}
+
+#[derive(Deserialize)]
+struct CrateData {
+    created_at: String,
+    downloads: u64,
+}
+
+fn parse_response(body: &str) -> Result<Published> {
+    let response: CrateResponse = serde_json::from_str(body)?;
+    let created_at = DateTime::parse_from_rfc3339(&response.krate.created_at)
+        .with_context(|| format!("Invalid date {:?}", response.krate.created_at))?;
+    Ok(Published {
+        created_at: created_at.with_timezone(&Utc),
+        downloads: response.krate.downloads,
+    })
+}
+
+/// Look a crate up on the crates.io API
+pub fn fetch_published(name: &str) -> Result<Published> {
+    let url = CRATES_IO_API.replace("{name}", name);
+    let body = cancel::fetch("crates.io lookup", {
+        let url = url.clone();
+        move |agent| {
+            agent
+                .get(&url)
+                // crates.io refuses API requests without a user agent
+                .set(
+                    "User-Agent",
+                    concat!("registry_checker/", env!("CARGO_PKG_VERSION")),
+                )
+                .call()
+                .with_context(|| format!("Failed to fetch {}", url))?
+                .into_string()
+                .with_context(|| format!("Failed to read {}", url))
+        }
+    })?;
+    parse_response(&body).with_context(|| format!("Failed to parse the response of {}", url))
+}
+
+/// Popular crate names, one per line (`#` comments and blank lines ignored)
+pub fn load_popular(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Could not read popular crates file {:?}", path))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Edits (insertions, deletions, substitutions, swaps of adjacent characters) turning one
+/// name into the other
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows i-2, i-1 and i of the optimal string alignment table
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// crates.io treats `-` and `_` as the same character in names
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace('_', "-")
+}
+
+/// Edits tolerated before two names stop looking alike; very short names are never flagged
+fn max_distance(name: &str) -> usize {
+    match name.chars().count() {
+        0..4 => 0,
+        4..8 => 1,
+        _ => 2,
+    }
+}
+
+/// The known crate a name most closely resembles, with the number of edits
+fn resembles<'a>(name: &str, known: &'a BTreeSet<String>) -> Option<(&'a str, usize)> {
+    let normalized = normalize(name);
+    let limit = max_distance(&normalized);
+    if limit == 0 || known.iter().any(|k| normalize(k) == normalized) {
+        return None;
+    }
+    known
+        .iter()
+        .map(|k| (k.as_str(), distance(&normalized, &normalize(k))))
+        .filter(|(_, edits)| *edits <= limit)
+        .min_by_key(|(_, edits)| *edits)
+}
+
+/// Why a crate awaiting approval may be a typosquat; empty when nothing stands out
+///
+/// `known` holds trusted names (registry crates and popular crates), `published` the
+/// crates.io data when it could be fetched.
+pub fn signals(
+    name: &str,
+    known: &BTreeSet<String>,
+    published: Option<&Published>,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let mut signals = Vec::new();
+    if let Some((similar, edits)) = resembles(name, known) {
+        signals.push(format!(
+            "name resembles {} ({} edit{})",
+            similar,
+            edits,
+            if edits == 1 { "" } else { "s" }
+        ));
+    }
+    if let Some(published) = published {
+        let age = (now - published.created_at).num_days();
+        if age < RECENT_DAYS {
+            signals.push(format!("first published {} day(s) ago", age));
+        }
+        if published.downloads < LOW_DOWNLOADS {
+            signals.push(format!("only {} downloads", published.downloads));
+        }
+    }
+    signals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("serde", "serde"), 0);
+        assert_eq!(distance("serde", "serd"), 1);
+        assert_eq!(distance("serde", "sedre"), 1);
+        assert_eq!(distance("tokio", "tokyo"), 1);
+        assert_eq!(distance("rand", "rand-core"), 5);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_signals() {
+        let known: BTreeSet<String> = ["serde", "serde_json", "tokio", "log"]
+            .map(String::from)
+            .into();
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            signals("serde-jsom", &known, None, now),
+            vec!["name resembles serde_json (1 edit)"]
+        );
+        assert_eq!(
+            signals("tokoi", &known, None, now),
+            vec!["name resembles tokio (1 edit)"]
+        );
+        // Same name to crates.io, and too short to judge
+        assert!(signals("serde-json", &known, None, now).is_empty());
+        assert!(signals("lag", &known, None, now).is_empty());
+
+        let fresh = Published {
+            created_at: Utc.with_ymd_and_hms(2026, 10, 3, 12, 0, 0).unwrap(),
+            downloads: 42,
+        };
+        assert_eq!(
+            signals("anyhow", &known, Some(&fresh), now),
+            vec!["first published 11 day(s) ago", "only 42 downloads"]
+        );
+        let established = Published {
+            created_at: Utc.with_ymd_and_hms(2019, 10, 7, 0, 0, 0).unwrap(),
+            downloads: 400_000_000,
+        };
+        assert!(signals("anyhow", &known, Some(&established), now).is_empty());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let published = parse_response(
+            r#"{"crate": {"name": "serde", "created_at": "2014-12-05T20:20:39.487502+00:00",
+                "downloads": 700000000, "recent_downloads": 90000000}, "versions": []}"#,
+        )
+        .unwrap();
+        assert_eq!(published.downloads, 700_000_000);
+        assert_eq!(published.created_at.timestamp(), 1417810839);
+        assert!(parse_response(r#"{"errors": [{"detail": "Not Found"}]}"#).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::cancel;
+use crate::parallel;
+use crate::parse_crate_name_version;
+use crate::registry::Registry;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Where crates are downloaded from; `{name}` and `{version}` are substituted
+pub const CRATES_IO_DOWNLOAD: &str =
+    "https://static.crates.io/crates/{name}/{name}-{version}.crate";
+
+/// Result of comparing one registry copy with the upstream download
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Identical,
+    /// The registry copy differs from upstream: possible tampering
+    Diverged {
+        local: usize,
+        upstream: usize,
+    },
+    /// The registry listing names a crate file the store does not have
+    MissingLocal,
+    /// Upstream could not be reached (offline, removed crate, ...)
+    Unavailable(String),
+}
+
+pub fn download_url(template: &str, name: &str, version: &str) -> String {
+    template
+        .replace("{name}", name)
+        .replace("{version}", version)
+}
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let url = url.to_string();
+    cancel::fetch("download", move |agent| {
+        let mut bytes = Vec::new();
+        agent
+            .get(&url)
+            .call()
+            .with_context(|| format!("Failed to download {}", url))?
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("Failed to read {}", url))?;
+        Ok(bytes)
+    })
+}
+
+/// Compare the stored crate file with what `fetch` returns for it
+fn verify_entry(local: &Path, fetch: impl FnOnce() -> Result<Vec<u8>>) -> Outcome {
+    let Ok(local) = fs::read(local) else {
+        return Outcome::MissingLocal;
+    };
+    match fetch() {
+        Ok(upstream) if upstream == local => Outcome::Identical,
+        Ok(upstream) => Outcome::Diverged {
+            local: local.len(),
+            upstream: upstream.len(),
+        },
+        Err(err) => Outcome::Unavailable(format!("{:#}", err)),
+    }
+}
+
+/// Re-download registry crates and byte-compare them with the offline store
+///
+/// Fails when any stored crate differs from upstream; unreachable downloads only warn
+pub fn verify(
+    registry_file: &Path,
+    crate_dir: &Path,
+    url_template: &str,
+    only: &[String],
+    jobs: usize,
+) -> Result<()> {
+    let registry = Registry::load(registry_file)?;
+    let mut entries: Vec<(&String, String)> = registry
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let (name, version) = parse_crate_name_version(entry)?;
+            (only.is_empty() || only.contains(&name)).then(|| {
+                (
+                    entry,
+                    download_url(url_template, &name, &version.to_string()),
+                )
+            })
+        })
+        .collect();
+    entries.sort();
+
+    eprintln!("Verifying {} crate(s)...", entries.len());
+    let outcomes = parallel::map(&entries, jobs, |(crate_file, url)| {
+        verify_entry(&crate_dir.join(crate_file), || download(url))
+    });
+    // Downloads left after Ctrl+C were skipped, the outcome would be misleading
+    cancel::check("verify")?;
+
+    let mut diverged = Vec::new();
+    let (mut identical, mut missing, mut unavailable) = (0, 0, 0);
+    for ((crate_file, url), outcome) in entries.iter().zip(outcomes) {
+        match outcome {
+            Outcome::Identical => identical += 1,
+            Outcome::Diverged { local, upstream } => {
+                diverged.push(format!(
+                    "{} ({} bytes in the registry, {} bytes from {})",
+                    crate_file, local, upstream, url
+                ));
+            }
+            Outcome::MissingLocal => {
+                missing += 1;
+                println!("  {}: not found in {:?}", crate_file, crate_dir);
+            }
+            Outcome::Unavailable(reason) => {
+                unavailable += 1;
+                println!("  {}: could not verify: {}", crate_file, reason);
+            }
+        }
+    }
+
+    println!(
+        "\n{} identical, {} diverged, {} missing from the store, {} could not be downloaded",
+        identical,
+        diverged.len(),
+        missing,
+        unavailable
+    );
+
+    if !diverged.is_empty() {
+        println!("\n========================================");
+        println!("!!! CRATES DIFFER FROM UPSTREAM: POSSIBLE TAMPERING !!!");
+        println!("========================================");
+        for line in &diverged {
+            println!("  - {}", line);
+        }
+        println!("========================================");
+        anyhow::bail!("{} registry crate(s) differ from upstream", diverged.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_url() {
+        assert_eq!(
+            download_url(CRATES_IO_DOWNLOAD, "serde", "1.0.228"),
+            "https://static.crates.io/crates/serde/serde-1.0.228.crate"
+        );
+    }
+
+    #[test]
+    fn test_verify_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let local = dir.join("serde-1.0.228.crate");
+        fs::write(&local, b"crate bytes").unwrap();
+
+        assert_eq!(
+            verify_entry(&local, || Ok(b"crate bytes".to_vec())),
+            Outcome::Identical
+        );
+        assert_eq!(
+            verify_entry(&local, || Ok(b"tampered crate bytes".to_vec())),
+            Outcome::Diverged {
+                local: 11,
+                upstream: 20
+            }
+        );
+        assert!(matches!(
+            verify_entry(&local, || anyhow::bail!("offline")),
+            Outcome::Unavailable(reason) if reason == "offline"
+        ));
+        assert_eq!(
+            verify_entry(&dir.join("anyhow-1.0.100.crate"), || Ok(Vec::new())),
+            Outcome::MissingLocal
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::cancel;
+use crate::cargo;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A package belonging to the workspace
+#[derive(Debug, Clone, Deserialize)]
+pub struct Member {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    id: String,
+}
+
+/// Subset of `cargo metadata --no-deps` output we need
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Member>,
+    workspace_members: Vec<String>,
+    /// Missing on cargo versions before 1.71
+    #[serde(default)]
+    workspace_default_members: Option<Vec<String>>,
+    workspace_root: PathBuf,
+}
+
+/// Which workspace members to check, mirroring cargo's package selection flags
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Check all members instead of the default members
+    pub workspace: bool,
+    /// Members to skip (only with `workspace`)
+    pub exclude: Vec<String>,
+}
+
+impl Selection {
+    /// Package selection arguments to pass on to cargo
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.workspace {
+            args.push("--workspace".to_string());
+            for member in &self.exclude {
+                args.push("--exclude".to_string());
+                args.push(member.clone());
+            }
+        }
+        args
+    }
+}
+
+#[derive(Debug)]
+pub struct Workspace {
+    pub root_manifest: PathBuf,
+    pub members: Vec<Member>,
+    default_members: Vec<String>,
+}
+
+impl Workspace {
+    /// Load the workspace containing the manifest via `cargo metadata`
+    pub fn load(manifest_path: &Path) -> Result<Workspace> {
+        let output = cancel::output(
+            cargo::command("metadata")
+                .arg("--manifest-path")
+                .arg(manifest_path)
+                .arg("--format-version")
+                .arg("1")
+                .arg("--no-deps"),
+            "cargo metadata",
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("cargo metadata failed: {}", stderr);
+        }
+
+        let metadata: Metadata =
+            serde_json::from_slice(&output.stdout).context("Could not parse cargo metadata")?;
+        Ok(Workspace::from_metadata(metadata))
+    }
+
+    fn from_metadata(metadata: Metadata) -> Workspace {
+        let members: Vec<Member> = metadata
+            .packages
+            .into_iter()
+            .filter(|package| metadata.workspace_members.contains(&package.id))
+            .collect();
+        let default_members = metadata
+            .workspace_default_members
+            .unwrap_or(metadata.workspace_members);
+
+        Workspace {
+            root_manifest: metadata.workspace_root.join("Cargo.toml"),
+            members,
+            default_members,
+        }
+    }
+
+    /// Members cargo would operate on for this manifest and selection
+    ///
+    /// Like cargo: `--workspace` selects every member except the excluded ones;
+    /// otherwise a package manifest selects that package and a virtual manifest
+    /// selects the default members
+    pub fn selected_members(&self, manifest_path: &Path, selection: &Selection) -> Vec<&Member> {
+        if selection.workspace {
+            for excluded in &selection.exclude {
+                if !self.members.iter().any(|m| &m.name == excluded) {
+                    eprintln!(
+                        "WARNING: excluded package `{}` not found in workspace",
+                        excluded
+                    );
+                }
+            }
+            return self
+                .members
+                .iter()
+                .filter(|m| !selection.exclude.contains(&m.name))
+                .collect();
+        }
+
+        let manifest = fs::canonicalize(manifest_path).unwrap_or(manifest_path.to_path_buf());
+        if let Some(member) = self.members.iter().find(|m| {
+            fs::canonicalize(&m.manifest_path).unwrap_or(m.manifest_path.clone()) == manifest
+        }) {
+            return vec![member];
+        }
+
+        self.members
+            .iter()
+            .filter(|m| self.default_members.contains(&m.id))
+            .collect()
+    }
+}
+
+/// Path relative to the current directory when possible, for display
+pub fn display_path(path: &Path) -> PathBuf {
+    env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_workspace() -> Workspace {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "packages": [
+                    {"name": "api", "id": "path+file:///ws/crates/api#0.1.0", "manifest_path": "/ws/crates/api/Cargo.toml"},
+                    {"name": "cli", "id": "path+file:///ws/crates/cli#0.1.0", "manifest_path": "/ws/crates/cli/Cargo.toml"},
+                    {"name": "fuzz", "id": "path+file:///ws/fuzz#0.1.0", "manifest_path": "/ws/fuzz/Cargo.toml"}
+                ],
+                "workspace_members": [
+                    "path+file:///ws/crates/api#0.1.0",
+                    "path+file:///ws/crates/cli#0.1.0",
+                    "path+file:///ws/fuzz#0.1.0"
+                ],
+                "workspace_default_members": [
+                    "path+file:///ws/crates/api#0.1.0",
+                    "path+file:///ws/crates/cli#0.1.0"
+                ],
+                "workspace_root": "/ws"
+            }"#,
+        )
+        .unwrap();
+        Workspace::from_metadata(metadata)
+    }
+
+    fn names(members: Vec<&Member>) -> Vec<&str> {
+        members.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_selected_members() {
+        let workspace = sample_workspace();
+        let root = Path::new("/ws/Cargo.toml");
+        assert_eq!(workspace.root_manifest, root);
+
+        // Virtual manifest: default members only
+        let selected = workspace.selected_members(root, &Selection::default());
+        assert_eq!(names(selected), vec!["api", "cli"]);
+
+        // --workspace --exclude fuzz
+        let selection = Selection {
+            workspace: true,
+            exclude: vec!["fuzz".to_string()],
+        };
+        assert_eq!(
+            names(workspace.selected_members(root, &selection)),
+            vec!["api", "cli"]
+        );
+        assert_eq!(
+            selection.cargo_args(),
+            vec!["--workspace", "--exclude", "fuzz"]
+        );
+
+        let selection = Selection {
+            workspace: true,
+            exclude: Vec::new(),
+        };
+        assert_eq!(
+            names(workspace.selected_members(root, &selection)),
+            vec!["api", "cli", "fuzz"]
+        );
+
+        // A member manifest selects just that member
+        let selected =
+            workspace.selected_members(Path::new("/ws/fuzz/Cargo.toml"), &Selection::default());
+        assert_eq!(names(selected), vec!["fuzz"]);
+    }
+}
@@ -0,0 +1,41 @@
+//! Runs the binary end to end, so what lands on its stdout can be checked
+
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+#[test]
+fn test_plugin_output_stays_off_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("registry.txt"), "").unwrap();
+    let plugin = root.join("plugin.sh");
+    fs::write(&plugin, "#!/bin/sh\necho plugin says hi\ncat >/dev/null\n").unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_registry_checker"))
+        .current_dir(root)
+        .args(["-r", "registry.txt", "-o", "json", "--plugin"])
+        .arg(&plugin)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(report["missing"].as_array().unwrap().is_empty());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("plugin says hi"),
+        "{:?}",
+        output.stderr
+    );
+}